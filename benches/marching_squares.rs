@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use perilous::marching_squares::{
+    marching_squares, marching_squares_contours, marching_squares_indexed, matrix::Matrix,
+    tiles::Tiles, MarchingSquaresSettings,
+};
+
+/// a field with lots of small blobs, so most rows have something to emit.
+fn blobs(size: usize) -> Matrix<f32, 2> {
+    Matrix::from_fn([size, size], |[x, y]| {
+        let (x, y) = (x as f32 * 0.15, y as f32 * 0.15);
+        x.sin() * y.cos() + 0.2
+    })
+}
+
+fn bench_marching_squares(c: &mut Criterion) {
+    let mut group = c.benchmark_group("marching_squares");
+    group.sample_size(10);
+    for size in [256, 1024] {
+        let densities = blobs(size);
+        let tiles = Tiles::new(&densities, 1.0);
+        for parallel in [false, true] {
+            let settings = MarchingSquaresSettings {
+                parallel,
+                ..Default::default()
+            };
+            let name = if parallel { "parallel" } else { "sequential" };
+            group.bench_with_input(
+                BenchmarkId::new(format!("soup/{name}"), size),
+                &settings,
+                |b, settings| b.iter(|| marching_squares(&tiles, *settings)),
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("indexed/{name}"), size),
+                &settings,
+                |b, settings| b.iter(|| marching_squares_indexed(&tiles, *settings)),
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("contours/{name}"), size),
+                &settings,
+                |b, settings| b.iter(|| marching_squares_contours(&tiles, *settings)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_marching_squares);
+criterion_main!(benches);
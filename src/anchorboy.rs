@@ -4,30 +4,103 @@ use bevy::{
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
+use bevy_rapier2d::prelude::*;
 use rand::random;
 
-use crate::chain::{simulate_chain, Chain, ChainLink};
-
-#[derive(Component)]
-pub struct SnapLink((usize, usize));
+use crate::{
+    chain::{
+        collider::RoomBounds,
+        plugin::{ChainBounds, ChainLinkSprite, ChainRenderer},
+        rope::RopeBundle,
+        Chain, ChainLink, ChainParams,
+    },
+    droplet::{Droplet, Hazard},
+    input::ActionState,
+};
 
+/// the anchor at the end of a chain, which anchor boy swings around.
 struct Tip {
+    /// index of the anchor's link in the chain, if the chain breaks before it
+    /// anchor boy has lost the anchor and can't steer it anymore.
+    link: usize,
     target: Vec2,
     velocity: Vec2,
 }
 
+/// what anchor boy is currently up to, every behavior lasts a set amount of time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Behavior {
+    /// waiting around with the anchors at its sides.
+    Idle,
+    /// follows the droplet at a distance, with the anchors trailing after it.
+    Track,
+    /// swings the anchors around itself.
+    Sweep,
+    /// throws the anchors at where the droplet was when the slam started.
+    Slam { target: Vec2 },
+    /// backs away from the droplet while reeling the anchors back in.
+    Retreat,
+}
+
+impl Behavior {
+    /// how long the behavior lasts, in seconds.
+    fn duration(self) -> f32 {
+        match self {
+            Behavior::Idle => 1.0,
+            Behavior::Track => 3.0,
+            Behavior::Sweep => 2.5,
+            Behavior::Slam { .. } => 1.2,
+            Behavior::Retreat => 1.5,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct AnchorBoy {
-    chains: Vec<(Chain, Tip)>,
+    /// the chain entities anchor boy is holding on to.
+    chains: Vec<(Entity, Tip)>,
     room_bounds: (Vec2, Vec2),
+    /// speed and acceleration of the anchors, in pixels per second (squared).
     max_chain_speed: f32,
     chain_accel: f32,
+    /// speed and acceleration of anchor boy himself, in pixels per second (squared).
     max_speed: f32,
     accel: f32,
+    velocity: Vec2,
+    /// starts out at `MAX_HEALTH` and goes down while the droplet attacks,
+    /// anchor boy gets more cautious as it does.
     health: f32,
+    /// anchor boy stays idle until the droplet gets close for the first time.
     active: bool,
+    behavior: Behavior,
+    /// seconds spent in the current behavior.
+    behavior_time: f32,
 }
 
+/// distance the droplet has to come within to wake anchor boy up.
+const WAKE_DIST: f32 = 350.0;
+
+/// anchor boy slams instead of sweeping when the droplet is closer than this.
+const SLAM_DIST: f32 = 300.0;
+
+/// how far anchor boy tries to stay from the droplet while tracking it.
+const TRACK_DIST: f32 = 220.0;
+
+/// radius of anchor boy's body.
+const BODY_RADIUS: f32 = 25.0;
+
+const MAX_HEALTH: f32 = 100.0;
+
+/// below this fraction of its health anchor boy stops sweeping, it only slams
+/// when the droplet comes close and backs off otherwise.
+const RETREAT_HEALTH: f32 = 0.4;
+
+/// how close to anchor boy's body the droplet has to be for its attack to land.
+const ATTACK_REACH: f32 = 40.0;
+
+/// damage per second the droplet deals to anchor boy while attacking within reach.
+const ATTACK_DAMAGE: f32 = 15.0;
+
 struct ChainSettings {
     len: usize,
     start_dist: f32,
@@ -36,18 +109,23 @@ struct ChainSettings {
     chain_radius: f32,
     anchor_len: f32,
     anchor_radius: f32,
+    anchor_inv_mass: f32,
+    break_threshold: f32,
 }
 
-fn generate_chain(settings: &ChainSettings) -> Vec<ChainLink> {
+fn generate_chain(settings: &ChainSettings) -> Chain {
     let mut chain = vec![];
     let mut angle = settings.start_angle;
     let mut dist = settings.start_dist;
     for i in 0..settings.len {
         let pt = Vec2::new(angle.cos(), angle.sin()) * dist;
-        let (len, radius) = if i == settings.len - 1 {
-            (settings.anchor_len, settings.anchor_radius)
+        let (len, radius, inv_mass) = if i == settings.len - 1 {
+            (settings.anchor_len, settings.anchor_radius, settings.anchor_inv_mass)
+        } else if i == 0 {
+            // the first link is held by anchor boy
+            (settings.chain_len, settings.chain_radius, 0.0)
         } else {
-            (settings.chain_len, settings.chain_radius)
+            (settings.chain_len, settings.chain_radius, 1.0)
         };
         let chain_dir = angle + PI / 2.0 + (random::<f32>() - 0.5);
         let chain_dir = Vec2::new(chain_dir.cos(), chain_dir.sin()) * len;
@@ -59,11 +137,17 @@ fn generate_chain(settings: &ChainSettings) -> Vec<ChainLink> {
             len,
             radius,
             constrain: true,
+            inv_mass,
+            tension: 0.0,
+            bend: None,
+            compliance: 0.0,
         };
         chain.push(link);
         dist = next_chain_pt.length();
         angle = next_chain_pt.to_angle();
     }
+    let mut chain = Chain::new(chain);
+    chain.break_threshold = Some(settings.break_threshold);
     chain
 }
 
@@ -72,6 +156,7 @@ pub fn setup_anchor_boy(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     server: Res<AssetServer>,
+    renderer: Res<ChainRenderer>,
 ) {
     let mut chain_settings = ChainSettings {
         len: 39,
@@ -81,60 +166,91 @@ pub fn setup_anchor_boy(
         chain_radius: 5.0,
         anchor_len: 90.0,
         anchor_radius: 40.0,
+        anchor_inv_mass: 0.1,
+        break_threshold: 1.0,
     };
-    let chain_left = generate_chain(&chain_settings);
-    chain_settings.start_angle = PI;
-    let chain_right = generate_chain(&chain_settings);
-
-    let anchor_boy = AnchorBoy {
-        chains: vec![
-            (
-                Chain(chain_left),
-                Tip {
-                    target: Vec2::ZERO,
-                    velocity: Vec2::ZERO,
-                },
-            ),
-            (
-                Chain(chain_right),
-                Tip {
-                    target: Vec2::ZERO,
-                    velocity: Vec2::ZERO,
-                },
-            ),
-        ],
-        room_bounds: (Vec2::ZERO, Vec2::ZERO),
-        max_chain_speed: 200.0,
-        chain_accel: 100.0,
-        max_speed: 10.0,
-        accel: 3.0,
-        health: 100.0,
-        active: false,
+    let chain_params = ChainParams {
+        damping: 0.5,
+        substeps: 2,
+        iterations: 5,
+        self_collision: true,
+        chain_collision: true,
+        ..default()
     };
-
-    for i in 0..anchor_boy.chains.len() {
-        for j in 1..anchor_boy.chains[i].0 .0.len() {
-            let res_name = if j == anchor_boy.chains[i].0 .0.len() - 1 {
-                "anchor.png"
+    let room = ChainBounds(RoomBounds {
+        min: Vec2::new(-500.0, -500.0),
+        max: Vec2::new(500.0, 500.0),
+    });
+    let mut chains = Vec::new();
+    for start_angle in [0.0, PI] {
+        chain_settings.start_angle = start_angle;
+        let chain = generate_chain(&chain_settings);
+        let link_count = chain.links.len();
+        let chain = commands.spawn((chain, chain_params.clone(), room)).id();
+        if *renderer == ChainRenderer::Ropes {
+            let mut rope = RopeBundle::new(chain, Color::BLACK);
+            // the anchor sprite goes on top of the end of the rope
+            rope.shape.spatial.transform.translation.z = -0.1;
+            commands.spawn(rope);
+        }
+        for j in 1..link_count {
+            if *renderer == ChainRenderer::Ropes && j != link_count - 1 {
+                continue;
+            }
+            let (res_name, overlap) = if j == link_count - 1 {
+                ("anchor.png", 1.0)
             } else if j % 2 == 0 {
-                "chain_0.png"
+                ("chain_0.png", 1.5)
             } else {
-                "chain_1.png"
+                ("chain_1.png", 1.5)
             };
 
             let img = server.load(res_name);
             commands.spawn((
                 SpriteBundle {
                     texture: img,
+                    sprite: Sprite {
+                        color: Color::BLACK,
+                        ..Default::default()
+                    },
                     visibility: Visibility::Hidden,
                     ..Default::default()
                 },
-                SnapLink((i, j)),
+                ChainLinkSprite {
+                    chain,
+                    link: j,
+                    image_size: 512.0,
+                    overlap,
+                },
             ));
         }
+        chains.push((
+            chain,
+            Tip {
+                link: link_count - 1,
+                target: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+            },
+        ));
     }
 
-    let circle = Mesh2dHandle(meshes.add(Circle { radius: 25.0 }));
+    let anchor_boy = AnchorBoy {
+        chains,
+        room_bounds: (Vec2::new(-400.0, -400.0), Vec2::new(400.0, 400.0)),
+        max_chain_speed: 400.0,
+        chain_accel: 1200.0,
+        max_speed: 80.0,
+        accel: 160.0,
+        velocity: Vec2::ZERO,
+        health: MAX_HEALTH,
+        active: false,
+        behavior: Behavior::Idle,
+        behavior_time: 0.0,
+    };
+
+    let circle = Mesh2dHandle(meshes.add(Circle {
+        radius: BODY_RADIUS,
+    }));
     let handles = commands
         .spawn((
             SpatialBundle {
@@ -180,6 +296,9 @@ pub fn setup_anchor_boy(
                 material: materials.add(Color::BLACK),
                 ..Default::default()
             },
+            RigidBody::KinematicPositionBased,
+            Collider::ball(BODY_RADIUS),
+            Hazard { damage: 10.0 },
         ))
         .with_children(|parent| {
             let anchor = server.load("anchor.png");
@@ -203,56 +322,128 @@ pub fn set_angle_according_to_spin(mut spin: Query<(&AnchorSpin, &mut Transform)
     transform.rotation = Quat::from_rotation_z(angle);
 }
 
+/// changes `velocity` towards `desired` by at most `accel * dt`,
+/// never going faster than `max_speed`.
+fn steer(velocity: Vec2, desired: Vec2, max_speed: f32, accel: f32, dt: f32) -> Vec2 {
+    let desired = desired.clamp_length_max(max_speed);
+    velocity + (desired - velocity).clamp_length_max(accel * dt)
+}
+
+/// velocity that heads towards the target, slowing down in time to stop on top of it.
+fn arrive(from: Vec2, to: Vec2, max_speed: f32, accel: f32) -> Vec2 {
+    let offset = to - from;
+    // the fastest speed that can still be brought to a stop over the remaining distance
+    let speed = (2.0 * accel * offset.length()).sqrt().min(max_speed);
+    offset.normalize_or_zero() * speed
+}
+
+/// picks what to do next once the current behavior is over.
+fn next_behavior(behavior: Behavior, pos: Vec2, droplet_pos: Vec2, health: f32) -> Behavior {
+    let hurt = health < MAX_HEALTH * RETREAT_HEALTH;
+    match behavior {
+        Behavior::Idle => Behavior::Track,
+        Behavior::Track if pos.distance(droplet_pos) < SLAM_DIST && (hurt || random::<bool>()) => {
+            Behavior::Slam {
+                target: droplet_pos,
+            }
+        }
+        Behavior::Track if hurt => Behavior::Retreat,
+        Behavior::Track => Behavior::Sweep,
+        Behavior::Sweep | Behavior::Slam { .. } => Behavior::Retreat,
+        Behavior::Retreat => Behavior::Idle,
+    }
+}
+
 pub fn anchor_boy(
+    time: Res<Time>,
     mut anchor_boy: Query<(&mut AnchorBoy, &mut Transform)>,
     mut spin: Query<&mut AnchorSpin, Without<AnchorBoy>>,
+    mut chains: Query<&mut Chain>,
+    droplet: Query<&Transform, (With<Droplet>, Without<AnchorBoy>)>,
+    actions: Res<ActionState>,
 ) {
     let (mut anchor_boy, mut anchor_boy_transform) = anchor_boy.single_mut();
-    for (chain, tip) in &mut anchor_boy.chains {
-        simulate_chain(
-            chain,
-            (Vec2::new(-500.0, -500.0), Vec2::new(500.0, 500.0)),
-            10,
-        );
+    let dt = time.delta_seconds();
+    let pos = anchor_boy_transform.translation.xy();
+    let droplet_pos = droplet.single().translation.xy();
+    let to_droplet = droplet_pos - pos;
+
+    if !anchor_boy.active && to_droplet.length() < WAKE_DIST {
+        anchor_boy.active = true;
+    }
+    if actions.attack && to_droplet.length() < BODY_RADIUS + ATTACK_REACH {
+        anchor_boy.health = (anchor_boy.health - ATTACK_DAMAGE * dt).max(0.0);
+    }
+    anchor_boy.behavior_time += dt;
+    if anchor_boy.active && anchor_boy.behavior_time > anchor_boy.behavior.duration() {
+        anchor_boy.behavior =
+            next_behavior(anchor_boy.behavior, pos, droplet_pos, anchor_boy.health);
+        anchor_boy.behavior_time = 0.0;
     }
+
+    // move the body
+    let desired_velocity = match anchor_boy.behavior {
+        Behavior::Track => {
+            let target = droplet_pos - to_droplet.normalize_or_zero() * TRACK_DIST;
+            arrive(pos, target, anchor_boy.max_speed, anchor_boy.accel)
+        }
+        Behavior::Retreat => -to_droplet.normalize_or_zero() * anchor_boy.max_speed,
+        Behavior::Idle | Behavior::Sweep | Behavior::Slam { .. } => Vec2::ZERO,
+    };
+    anchor_boy.velocity = steer(
+        anchor_boy.velocity,
+        desired_velocity,
+        anchor_boy.max_speed,
+        anchor_boy.accel,
+        dt,
+    );
+    let (min, max) = anchor_boy.room_bounds;
+    let new_pos = (pos + anchor_boy.velocity * dt).clamp(min + BODY_RADIUS, max - BODY_RADIUS);
+    // running into the edge of the room stops anchor boy
+    anchor_boy.velocity = (new_pos - pos) / dt.max(f32::EPSILON);
+    anchor_boy_transform.translation = new_pos.extend(anchor_boy_transform.translation.z);
+
     let mut spin = spin.single_mut();
     spin.angle += 0.1;
-    let pos = Vec2::new(spin.angle.cos(), spin.angle.sin()) * 30.0;
-    anchor_boy.chains[0].0.0[0].loc = pos + anchor_boy_transform.translation.xy();
-    let pos = Vec2::new((spin.angle + PI).cos(), (spin.angle + PI).sin()) * 30.0;
-    anchor_boy.chains[1].0.0[0].loc = pos + anchor_boy_transform.translation.xy();
 
-    anchor_boy_transform.translation += Vec3::new(1.0, 0.0, 0.0);
-}
-
-pub fn snap_links_to_chains(
-    anchor_boy: Query<(&AnchorBoy, &Transform)>,
-    mut links_to_snap: Query<(&SnapLink, &mut Transform), Without<AnchorBoy>>,
-) {
-    let (anchor_boy, anchor_boy_transform) = anchor_boy.single();
-    for (&SnapLink((chain_id, link_id)), mut link_transform) in links_to_snap.iter_mut() {
-        let chain_loc =
-            anchor_boy.chains[chain_id].0 .0[link_id].loc;
-        let prev_chain_loc = anchor_boy.chains[chain_id].0 .0[link_id - 1].loc;
-        let angle = (chain_loc - prev_chain_loc).to_angle();
-        let loc = (chain_loc + prev_chain_loc) / 2.0;
-        let scale_factor = anchor_boy.chains[chain_id].0 .0[link_id].len;
-        if link_id == anchor_boy.chains[chain_id].0 .0.len() - 1 {
-            link_transform.scale = Vec3::new(scale_factor / 512.0, scale_factor / 512.0, 1.0);
-        } else {
-            link_transform.scale =
-                Vec3::new(scale_factor * 1.5 / 512.0, scale_factor * 1.5 / 512.0, 1.0);
-        }
-        link_transform.translation = Vec3::new(loc.x, loc.y, 0.0);
-        link_transform.rotation = Quat::from_rotation_z(angle + PI / 2.0);
-    }
-}
+    // move the anchors
+    let AnchorBoy {
+        chains: held,
+        behavior,
+        behavior_time,
+        max_chain_speed,
+        chain_accel,
+        ..
+    } = &mut *anchor_boy;
+    for (i, (chain, tip)) in held.iter_mut().enumerate() {
+        let Ok(mut chain) = chains.get_mut(*chain) else {
+            continue;
+        };
+        let side = PI * i as f32;
+        let angle = spin.angle + side;
+        chain.links[0].loc = new_pos + Vec2::new(angle.cos(), angle.sin()) * 30.0;
 
-pub fn set_link_properties(mut links: Query<(&mut Visibility, &mut Sprite), With<SnapLink>>) {
-    for (mut visibility, mut sprite) in links.iter_mut() {
-        *visibility = Visibility::Visible;
-        sprite.color.set_r(0.0);
-        sprite.color.set_g(0.0);
-        sprite.color.set_b(0.0);
+        let Some(anchor) = chain.links.get_mut(tip.link) else {
+            continue;
+        };
+        tip.target = match *behavior {
+            Behavior::Idle => new_pos + Vec2::from_angle(side) * 120.0,
+            Behavior::Track => droplet_pos + Vec2::from_angle(side) * 60.0,
+            Behavior::Sweep => new_pos + Vec2::from_angle(*behavior_time * 2.5 + side) * 260.0,
+            Behavior::Slam { target } => target,
+            Behavior::Retreat => new_pos + Vec2::from_angle(side) * 80.0,
+        };
+        let desired = match *behavior {
+            // slams don't slow down as they get close
+            Behavior::Slam { .. } => {
+                (tip.target - anchor.loc).normalize_or_zero() * *max_chain_speed
+            }
+            _ => arrive(anchor.loc, tip.target, *max_chain_speed, *chain_accel),
+        };
+        tip.velocity = steer(tip.velocity, desired, *max_chain_speed, *chain_accel, dt);
+        // the anchor is moved along with its previous location, so that the simulation
+        // doesn't also pick the movement up as velocity
+        anchor.loc += tip.velocity * dt;
+        anchor.prev_loc += tip.velocity * dt;
     }
 }
@@ -0,0 +1,238 @@
+use bevy::math::Vec2;
+
+use crate::marching_squares::Contour;
+
+/// something chain links can collide with. Links are treated as circles.
+pub trait ChainCollider {
+    /// returns where a circle with the provided center and radius needs to be moved to
+    /// so that it no longer overlaps the collider, or None if it isn't overlapping.
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2>;
+}
+
+/// keeps links inside of an axis aligned rectangle.
+#[derive(Clone, Copy, PartialEq)]
+pub struct RoomBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl ChainCollider for RoomBounds {
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        let resolved = center.clamp(self.min + radius, self.max - radius);
+        (resolved != center).then_some(resolved)
+    }
+}
+
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl ChainCollider for Circle {
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        push_out_of_point(center, radius + self.radius, self.center)
+    }
+}
+
+/// a line segment with rounded ends.
+pub struct Capsule {
+    pub a: Vec2,
+    pub b: Vec2,
+    pub radius: f32,
+}
+
+impl ChainCollider for Capsule {
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        let closest = closest_point_on_segment(center, self.a, self.b);
+        push_out_of_point(center, radius + self.radius, closest)
+    }
+}
+
+/// a convex polygon, the points must be in counter clockwise order.
+pub struct ConvexPolygon {
+    pub points: Vec<Vec2>,
+}
+
+impl ChainCollider for ConvexPolygon {
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        let edges = || {
+            let next = self.points.iter().cycle().skip(1);
+            self.points.iter().copied().zip(next.copied())
+        };
+        // the edge the center is furthest in front of, if it's in front of any edge
+        // then the center is outside of the polygon.
+        let (a, b, separation) = edges()
+            .map(|(a, b)| (a, b, (center - a).dot(outward_normal(a, b))))
+            .max_by(|x, y| x.2.total_cmp(&y.2))?;
+        if separation > radius {
+            return None;
+        }
+        if separation <= 0.0 {
+            return Some(center + outward_normal(a, b) * (radius - separation));
+        }
+        let closest = edges()
+            .map(|(a, b)| closest_point_on_segment(center, a, b))
+            .min_by(|x, y| x.distance_squared(center).total_cmp(&y.distance_squared(center)))?;
+        push_out_of_point(center, radius, closest)
+    }
+}
+
+/// the outlines from marching squares, the filled side of every contour is solid.
+/// the contours are offset by `offset`, since they're usually relative to the entity they're on.
+/// A field that's filled in everywhere has no contours, so it needs a `ConvexPolygon` instead.
+pub struct Contours {
+    /// the points of every outline, already offset, along with whether the outline is closed.
+    outlines: Vec<(Vec<Vec2>, bool)>,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Contours {
+    pub fn new(contours: &[Contour], offset: Vec2) -> Self {
+        let outlines = contours
+            .iter()
+            .map(|contour| {
+                let points = contour
+                    .points
+                    .iter()
+                    .map(|p| Vec2::new(p[0], p[1]) + offset)
+                    .collect::<Vec<_>>();
+                (points, contour.closed)
+            })
+            .collect::<Vec<_>>();
+        let (min, max) = outlines
+            .iter()
+            .flat_map(|(points, _)| points)
+            .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), p| {
+                (min.min(*p), max.max(*p))
+            });
+        Self { outlines, min, max }
+    }
+}
+
+impl ChainCollider for Contours {
+    fn resolve(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        if (center + radius).cmplt(self.min).any() || (center - radius).cmpgt(self.max).any() {
+            return None;
+        }
+        // the closest point on any outline, along with the direction of the filled side there.
+        let mut closest: Option<(f32, Vec2, Vec2)> = None;
+        for (points, closed) in &self.outlines {
+            let segment_count = if *closed {
+                points.len()
+            } else {
+                points.len().saturating_sub(1)
+            };
+            let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+            for i in 0..segment_count {
+                let (a, b) = segment(i);
+                let point = closest_point_on_segment(center, a, b);
+                let dist_squared = point.distance_squared(center);
+                if closest.is_some_and(|(closest_dist, _, _)| closest_dist <= dist_squared) {
+                    continue;
+                }
+                // the filled side is on the left, when the closest point is a corner the
+                // neighboring segment has a say in which side that is too.
+                let mut solid_dir = -outward_normal(a, b);
+                if point == a && (*closed || i > 0) {
+                    let (prev_a, prev_b) = segment((i + segment_count - 1) % segment_count);
+                    solid_dir -= outward_normal(prev_a, prev_b);
+                } else if point == b && (*closed || i + 1 < segment_count) {
+                    let (next_a, next_b) = segment((i + 1) % segment_count);
+                    solid_dir -= outward_normal(next_a, next_b);
+                }
+                closest = Some((dist_squared, point, solid_dir));
+            }
+        }
+        let (dist_squared, point, solid_dir) = closest?;
+        if (center - point).dot(solid_dir) > 0.0 {
+            // the center is inside, so it has to be pushed all the way through the outline.
+            return Some(point - solid_dir.normalize_or_zero() * radius);
+        }
+        if dist_squared >= radius * radius {
+            return None;
+        }
+        push_out_of_point(center, radius, point)
+    }
+}
+
+/// the normal pointing to the right of the segment from a to b,
+/// which is outwards for counter clockwise polygons.
+fn outward_normal(a: Vec2, b: Vec2) -> Vec2 {
+    let dir = (b - a).normalize_or_zero();
+    Vec2::new(dir.y, -dir.x)
+}
+
+fn closest_point_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len_squared = ab.length_squared();
+    if len_squared == 0.0 {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / len_squared).clamp(0.0, 1.0)
+}
+
+/// moves a circle so that it's at least `dist` away from the point.
+fn push_out_of_point(center: Vec2, dist: f32, point: Vec2) -> Option<Vec2> {
+    let offset = center - point;
+    let len_squared = offset.length_squared();
+    if len_squared >= dist * dist {
+        return None;
+    }
+    // if the center is exactly on the point there's no good direction, so any will do.
+    let dir = offset.try_normalize().unwrap_or(Vec2::X);
+    Some(point + dir * dist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle() {
+        let circle = Circle {
+            center: Vec2::ZERO,
+            radius: 10.0,
+        };
+        assert_eq!(
+            circle.resolve(Vec2::new(5.0, 0.0), 2.0),
+            Some(Vec2::new(12.0, 0.0))
+        );
+        assert_eq!(
+            circle.resolve(Vec2::new(0.0, -11.0), 2.0),
+            Some(Vec2::new(0.0, -12.0))
+        );
+        assert_eq!(circle.resolve(Vec2::new(13.0, 0.0), 2.0), None);
+    }
+
+    #[test]
+    fn convex_polygon() {
+        let square = ConvexPolygon {
+            points: vec![
+                Vec2::ZERO,
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+        };
+        // centers inside are pushed out through the closest edge
+        assert_eq!(
+            square.resolve(Vec2::new(5.0, 9.0), 1.0),
+            Some(Vec2::new(5.0, 11.0))
+        );
+        assert_eq!(
+            square.resolve(Vec2::new(2.0, 5.0), 1.0),
+            Some(Vec2::new(-1.0, 5.0))
+        );
+        // centers outside but overlapping an edge
+        assert_eq!(
+            square.resolve(Vec2::new(5.0, 10.5), 1.0),
+            Some(Vec2::new(5.0, 11.0))
+        );
+        assert_eq!(square.resolve(Vec2::new(5.0, 12.0), 1.0), None);
+        // or a corner, which pushes away from the corner rather than an edge
+        let resolved = square.resolve(Vec2::new(10.5, 10.5), 1.0).unwrap();
+        assert!((resolved.distance(Vec2::splat(10.0)) - 1.0).abs() < 1e-5);
+        assert_eq!(square.resolve(Vec2::new(10.8, 10.8), 1.0), None);
+    }
+}
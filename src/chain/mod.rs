@@ -0,0 +1,539 @@
+use bevy::{
+    ecs::{component::Component, event::Event},
+    math::Vec2,
+    prelude::Entity,
+};
+
+use self::{collider::ChainCollider, spatial_hash::SpatialHash};
+
+pub mod collider;
+pub mod plugin;
+pub mod rope;
+pub mod spatial_hash;
+
+pub struct ChainLink {
+    pub loc: Vec2,
+    pub prev_loc: Vec2,
+    pub len: f32,
+    pub radius: f32,
+    pub constrain: bool,
+    /// how easily the link gets moved by constraints, heavier links have smaller values.
+    /// links with 0 are pinned, they're never moved by the simulation,
+    /// only by whatever owns the chain.
+    pub inv_mass: f32,
+    /// how much further the link is from the previous one than it should be, relative to `len`,
+    /// after the last time the chain was simulated. always 0 for the first link.
+    pub tension: f32,
+    /// limits how much the chain can bend at this link, the first and last links can't bend.
+    pub bend: Option<Bend>,
+    /// how much the link stretches away from the previous one under load, 0 doesn't stretch at all.
+    /// only used by the `Xpbd` solver.
+    pub compliance: f32,
+}
+
+/// how the angle between the segment coming into a link and the one going out of it is constrained.
+/// angles are in radians, and 0 is a straight chain.
+#[derive(Clone, Copy)]
+pub enum Bend {
+    /// the chain is free to bend until it reaches this angle either way.
+    MaxAngle(f32),
+    /// the chain is pulled back towards the rest angle, a stiffness of 1
+    /// snaps it right back, lower stiffnesses take more iterations to get there.
+    Spring { rest_angle: f32, stiffness: f32 },
+}
+
+impl ChainLink {
+    pub fn is_pinned(&self) -> bool {
+        self.inv_mass == 0.0
+    }
+}
+
+/// how the distance constraints between links are solved.
+#[derive(Clone, Copy, Default)]
+pub enum Solver {
+    /// every iteration moves the links most of the way back to their lengths,
+    /// so the chain gets stiffer with more iterations.
+    #[default]
+    Pbd,
+    /// uses the compliance of each link, so the chain is as stretchy
+    /// as its links say no matter how many iterations or substeps there are.
+    Xpbd,
+}
+
+#[derive(Component)]
+pub struct Chain {
+    pub links: Vec<ChainLink>,
+    pub solver: Solver,
+    /// the chain breaks apart at any link whose tension goes over this, see `break_chain`.
+    pub break_threshold: Option<f32>,
+    /// length of the last step, used to scale the velocity if the step length changes.
+    prev_dt: Option<f32>,
+}
+
+impl Chain {
+    pub fn new(links: Vec<ChainLink>) -> Self {
+        Self {
+            links,
+            solver: Solver::default(),
+            break_threshold: None,
+            prev_dt: None,
+        }
+    }
+}
+
+/// sent when a chain breaks, the links that came off are moved to a new chain entity.
+#[derive(Event)]
+pub struct ChainBroken {
+    pub chain: Entity,
+    /// index of the first link that came off, in the chain that broke.
+    pub link: usize,
+    pub piece: Entity,
+}
+
+/// how a chain moves, independent of how often it gets simulated.
+/// chain entities without this use the default params.
+#[derive(Component, Clone, PartialEq)]
+pub struct ChainParams {
+    /// acceleration applied to every link, in units per second squared.
+    pub gravity: Vec2,
+    /// fraction of the velocity lost per second, 0 keeps it moving forever.
+    pub damping: f32,
+    /// amount of time to simulate, in seconds. Chain entities
+    /// ignore this, and are simulated using the fixed timestep.
+    pub dt: f32,
+    /// the step is split into this many smaller steps, more is more stable but slower.
+    pub substeps: usize,
+    /// how many times the constraints are solved every substep.
+    pub iterations: usize,
+    /// keep links of the same chain from passing through each other.
+    pub self_collision: bool,
+    /// keep links of different chains from passing through each other,
+    /// only has an effect when simulating multiple chains together. Chain entities
+    /// are simulated together with the other chain entities that have the same params and bounds.
+    pub chain_collision: bool,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            gravity: Vec2::ZERO,
+            damping: 0.0,
+            dt: 1.0 / 64.0,
+            substeps: 1,
+            iterations: 10,
+            self_collision: false,
+            chain_collision: false,
+        }
+    }
+}
+
+/// simulates a chain, pushing every link with `constrain` set out of the colliders.
+pub fn simulate_chain(
+    chain: &mut Chain,
+    colliders: &[&dyn ChainCollider],
+    params: &ChainParams,
+) {
+    simulate_chains(&mut [chain], colliders, params);
+}
+
+/// simulates multiple chains together, so that they can collide with each other.
+pub fn simulate_chains(
+    chains: &mut [&mut Chain],
+    colliders: &[&dyn ChainCollider],
+    params: &ChainParams,
+) {
+    let substeps = params.substeps.max(1);
+    let dt = params.dt / substeps as f32;
+    for _ in 0..substeps {
+        for chain in chains.iter_mut() {
+            step_chain(chain, colliders, params, dt);
+        }
+        if params.self_collision || params.chain_collision {
+            collide_links(chains, params.self_collision, params.chain_collision);
+        }
+    }
+    for chain in chains.iter_mut() {
+        update_tension(chain);
+    }
+}
+
+/// moves link i and the one after it so that they're `len` apart. With xpbd the constraint is
+/// softened by the compliance of the link, which needs its lagrange multiplier and the step length.
+fn solve_distance(links: &mut [ChainLink], i: usize, xpbd: Option<(&mut f32, f32)>) {
+    let (inv_mass_a, inv_mass_b) = (links[i].inv_mass, links[i + 1].inv_mass);
+    let offset = links[i].loc - links[i + 1].loc;
+    let dist = offset.length();
+    if inv_mass_a + inv_mass_b == 0.0 || dist == 0.0 {
+        return;
+    }
+    let error = dist - links[i + 1].len;
+    let delta_lambda = match xpbd {
+        None => -error / (inv_mass_a + inv_mass_b),
+        Some((lambda, dt)) => {
+            let alpha = links[i + 1].compliance / (dt * dt);
+            let delta_lambda = (-error - alpha * *lambda) / (inv_mass_a + inv_mass_b + alpha);
+            *lambda += delta_lambda;
+            delta_lambda
+        }
+    };
+    let translation = offset / dist * delta_lambda;
+    links[i].loc += translation * inv_mass_a;
+    links[i + 1].loc -= translation * inv_mass_b;
+}
+
+/// moves the link and its neighbors to bring the angle at the link within its bend limit,
+/// weighted by their inverse masses.
+fn apply_bend(links: &mut [ChainLink], i: usize) {
+    let Some(bend) = links[i].bend else {
+        return;
+    };
+    let incoming = links[i].loc - links[i - 1].loc;
+    let outgoing = links[i + 1].loc - links[i].loc;
+    if incoming == Vec2::ZERO || outgoing == Vec2::ZERO {
+        return;
+    }
+    // atan2 rather than `angle_between`, which can give NaN for nearly straight chains
+    let angle = incoming.perp_dot(outgoing).atan2(incoming.dot(outgoing));
+    let error = match bend {
+        Bend::MaxAngle(max) => angle - angle.clamp(-max, max),
+        Bend::Spring {
+            rest_angle,
+            stiffness,
+        } => (angle - rest_angle) * stiffness.clamp(0.0, 1.0),
+    };
+    if error == 0.0 {
+        return;
+    }
+    // how the angle changes as each of the three links moves
+    let grad_prev = incoming.perp() / incoming.length_squared();
+    let grad_next = outgoing.perp() / outgoing.length_squared();
+    let grad_pivot = -grad_prev - grad_next;
+    let inv_masses = [links[i - 1].inv_mass, links[i].inv_mass, links[i + 1].inv_mass];
+    let grads = [grad_prev, grad_pivot, grad_next];
+    let denominator = inv_masses
+        .iter()
+        .zip(grads)
+        .map(|(inv_mass, grad)| inv_mass * grad.length_squared())
+        .sum::<f32>();
+    if denominator == 0.0 {
+        return;
+    }
+    let scale = -error / denominator;
+    for (offset, (inv_mass, grad)) in inv_masses.into_iter().zip(grads).enumerate() {
+        links[i - 1 + offset].loc += grad * scale * inv_mass;
+    }
+}
+
+fn update_tension(chain: &mut Chain) {
+    if let Some(first) = chain.links.first_mut() {
+        first.tension = 0.0;
+    }
+    for i in 1..chain.links.len() {
+        let dist = chain.links[i].loc.distance(chain.links[i - 1].loc);
+        let link = &mut chain.links[i];
+        link.tension = if link.len > 0.0 {
+            (dist - link.len) / link.len
+        } else {
+            0.0
+        };
+    }
+}
+
+/// breaks the chain at the link under the most tension,
+/// if that's over the chain's break threshold. The chain keeps the links before the break,
+/// and the rest are returned as a new chain. Only breaks once, since the tension
+/// drops everywhere else once a chain snaps.
+pub fn break_chain(chain: &mut Chain) -> Option<Chain> {
+    let threshold = chain.break_threshold?;
+    let (index, _) = chain
+        .links
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, link)| link.tension > threshold)
+        .max_by(|(_, a), (_, b)| a.tension.total_cmp(&b.tension))?;
+    Some(Chain {
+        links: chain.links.split_off(index),
+        solver: chain.solver,
+        break_threshold: chain.break_threshold,
+        prev_dt: chain.prev_dt,
+    })
+}
+
+/// a single time-corrected verlet step, followed by solving the constraints.
+fn step_chain(
+    chain: &mut Chain,
+    colliders: &[&dyn ChainCollider],
+    params: &ChainParams,
+    dt: f32,
+) {
+    if dt <= 0.0 {
+        return;
+    }
+    // the previous step may have been a different length, in which case
+    // the distance travelled during it needs to be rescaled to this one.
+    let dt_ratio = chain.prev_dt.map_or(1.0, |prev_dt| dt / prev_dt);
+    chain.prev_dt = Some(dt);
+    let damping = (1.0 - params.damping).max(0.0).powf(dt);
+    let acceleration = params.gravity * dt * dt;
+
+    // update chain according to velocities
+    for link in chain.links.iter_mut() {
+        if link.is_pinned() {
+            link.prev_loc = link.loc;
+            continue;
+        }
+        let temp = link.loc;
+        link.loc += (link.loc - link.prev_loc) * dt_ratio * damping + acceleration;
+        link.prev_loc = temp;
+    }
+
+    // apply chain constraints
+    // xpbd keeps track of the total force (lagrange multiplier) applied by
+    // each constraint during the step, starting from nothing.
+    let mut lambdas = vec![0.0; chain.links.len()];
+    for _ in 0..params.iterations {
+        for i in 0..chain.links.len().saturating_sub(1) {
+            let xpbd = match chain.solver {
+                Solver::Pbd => None,
+                Solver::Xpbd => Some((&mut lambdas[i + 1], dt)),
+            };
+            solve_distance(&mut chain.links, i, xpbd);
+        }
+        for i in 1..chain.links.len().saturating_sub(1) {
+            apply_bend(&mut chain.links, i);
+        }
+    }
+
+    // push the links out of anything they ran into
+    for link in chain.links.iter_mut() {
+        if !link.constrain || link.is_pinned() {
+            continue;
+        }
+        for collider in colliders {
+            if let Some(loc) = collider.resolve(link.loc, link.radius) {
+                link.loc = loc;
+            }
+        }
+    }
+}
+
+/// pushes apart links with `constrain` set that overlap each other.
+/// links of the same chain that overlap even when the chain is stretched out
+/// are left alone, since they'd never stop pushing each other apart.
+fn collide_links(chains: &mut [&mut Chain], self_collision: bool, chain_collision: bool) {
+    // (chain, link) of every link that can collide, along with how far along its chain it is.
+    let mut ids = Vec::new();
+    let mut max_radius: f32 = 0.0;
+    for (chain_id, chain) in chains.iter().enumerate() {
+        let mut dist_along = 0.0;
+        for (link_id, link) in chain.links.iter().enumerate() {
+            if link_id > 0 {
+                dist_along += link.len;
+            }
+            if link.constrain {
+                ids.push((chain_id, link_id, dist_along));
+                max_radius = max_radius.max(link.radius);
+            }
+        }
+    }
+    if max_radius <= 0.0 {
+        return;
+    }
+
+    let mut hash = SpatialHash::new(max_radius * 2.0);
+    for (id, &(chain_id, link_id, _)) in ids.iter().enumerate() {
+        hash.insert(chains[chain_id].links[link_id].loc, id);
+    }
+    for (id, &(chain_a, link_a, along_a)) in ids.iter().enumerate() {
+        let loc = chains[chain_a].links[link_a].loc;
+        for other in hash.nearby(loc) {
+            // every pair would otherwise be handled twice
+            if other <= id {
+                continue;
+            }
+            let (chain_b, link_b, along_b) = ids[other];
+            let radius_a = chains[chain_a].links[link_a].radius;
+            let radius_b = chains[chain_b].links[link_b].radius;
+            let min_dist = radius_a + radius_b;
+            if chain_a == chain_b {
+                if !self_collision || (along_a - along_b).abs() < min_dist {
+                    continue;
+                }
+            } else if !chain_collision {
+                continue;
+            }
+
+            let diff = chains[chain_b].links[link_b].loc - chains[chain_a].links[link_a].loc;
+            let dist = diff.length();
+            if dist >= min_dist || dist == 0.0 {
+                continue;
+            }
+            let inv_mass_a = chains[chain_a].links[link_a].inv_mass;
+            let inv_mass_b = chains[chain_b].links[link_b].inv_mass;
+            let total_inv_mass = inv_mass_a + inv_mass_b;
+            if total_inv_mass == 0.0 {
+                continue;
+            }
+            let correction = diff / dist * (min_dist - dist) / total_inv_mass;
+            chains[chain_a].links[link_a].loc -= correction * inv_mass_a;
+            chains[chain_b].links[link_b].loc += correction * inv_mass_b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a chain of links `len` apart, going right from the origin.
+    fn straight_chain(count: usize, len: f32) -> Chain {
+        Chain::new(
+            (0..count)
+                .map(|i| {
+                    let loc = Vec2::new(i as f32 * len, 0.0);
+                    ChainLink {
+                        loc,
+                        prev_loc: loc,
+                        len,
+                        radius: 1.0,
+                        constrain: true,
+                        inv_mass: 1.0,
+                        tension: 0.0,
+                        bend: None,
+                        compliance: 0.0,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn overlapping_links_separate() {
+        let mut a = straight_chain(1, 10.0);
+        let mut b = straight_chain(1, 10.0);
+        a.links[0].radius = 5.0;
+        b.links[0].radius = 3.0;
+        b.links[0].loc = Vec2::new(2.0, 1.0);
+        b.links[0].prev_loc = b.links[0].loc;
+        let params = ChainParams {
+            chain_collision: true,
+            ..Default::default()
+        };
+        simulate_chains(&mut [&mut a, &mut b], &[], &params);
+        let dist = a.links[0].loc.distance(b.links[0].loc);
+        assert!(dist >= 8.0 - 1e-4, "{dist}");
+    }
+
+    #[test]
+    fn pinned_at_both_ends() {
+        let mut chain = straight_chain(11, 10.0);
+        chain.links[0].inv_mass = 0.0;
+        chain.links[10].inv_mass = 0.0;
+        // a little slack, so the chain can sag
+        for link in &mut chain.links {
+            link.len = 10.5;
+        }
+        let params = ChainParams {
+            gravity: Vec2::new(0.0, -500.0),
+            damping: 0.9,
+            substeps: 4,
+            ..Default::default()
+        };
+        for _ in 0..500 {
+            simulate_chain(&mut chain, &[], &params);
+        }
+        assert_eq!(chain.links[0].loc, Vec2::ZERO);
+        assert_eq!(chain.links[10].loc, Vec2::new(100.0, 0.0));
+        assert!(chain.links[5].loc.y < -10.0, "{}", chain.links[5].loc);
+    }
+
+    #[test]
+    fn breaks_when_stretched() {
+        let mut chain = straight_chain(10, 10.0);
+        chain.links[0].inv_mass = 0.0;
+        chain.links[9].inv_mass = 0.0;
+        let params = ChainParams::default();
+        simulate_chain(&mut chain, &[], &params);
+        // chains without a threshold never break
+        chain.links[9].loc = Vec2::new(300.0, 0.0);
+        simulate_chain(&mut chain, &[], &params);
+        assert!(break_chain(&mut chain).is_none());
+
+        chain.break_threshold = Some(0.5);
+        let tensions = chain
+            .links
+            .iter()
+            .map(|link| link.tension)
+            .collect::<Vec<_>>();
+        let most_stressed = (1..10)
+            .max_by(|a, b| tensions[*a].total_cmp(&tensions[*b]))
+            .unwrap();
+        assert!(tensions[most_stressed] > 0.5);
+        let piece = break_chain(&mut chain).unwrap();
+        // the chain keeps the links before the break, and the piece gets the rest
+        assert_eq!(chain.links.len(), most_stressed);
+        assert_eq!(piece.links.len(), 10 - most_stressed);
+        assert_eq!(chain.links[0].loc, Vec2::ZERO);
+        assert_eq!(piece.links.last().unwrap().loc, Vec2::new(300.0, 0.0));
+        assert_eq!(piece.break_threshold, Some(0.5));
+    }
+
+    #[test]
+    fn bend_limit() {
+        // held horizontally by its first two links, and left to droop under gravity
+        let mut chain = straight_chain(10, 10.0);
+        chain.links[0].inv_mass = 0.0;
+        chain.links[1].inv_mass = 0.0;
+        for link in &mut chain.links[1..9] {
+            link.bend = Some(Bend::MaxAngle(0.1));
+        }
+        let params = ChainParams {
+            gravity: Vec2::new(0.0, -100.0),
+            damping: 0.9,
+            substeps: 4,
+            iterations: 20,
+            ..Default::default()
+        };
+        for _ in 0..500 {
+            simulate_chain(&mut chain, &[], &params);
+        }
+        let links = &chain.links;
+        for i in 1..9 {
+            let incoming = links[i].loc - links[i - 1].loc;
+            let outgoing = links[i + 1].loc - links[i].loc;
+            let angle = incoming.angle_between(outgoing).abs();
+            assert!(angle < 0.11, "link {i} bent by {angle}");
+        }
+    }
+
+    #[test]
+    fn xpbd_stretch() {
+        // how far a hanging chain stretches with the provided number of iterations
+        let stretch = |iterations| {
+            let mut chain = straight_chain(10, 10.0);
+            chain.solver = Solver::Xpbd;
+            chain.links[0].inv_mass = 0.0;
+            for link in &mut chain.links {
+                link.loc = -link.loc.perp();
+                link.prev_loc = link.loc;
+                link.compliance = 0.001;
+            }
+            let params = ChainParams {
+                gravity: Vec2::new(0.0, -100.0),
+                damping: 0.99,
+                substeps: 4,
+                iterations,
+                ..Default::default()
+            };
+            for _ in 0..2000 {
+                simulate_chain(&mut chain, &[], &params);
+            }
+            -chain.links[9].loc.y - 90.0
+        };
+        let few = stretch(5);
+        let many = stretch(80);
+        assert!(few > 1.0, "{few}");
+        assert!((few - many).abs() < few * 0.01, "{few} {many}");
+    }
+}
@@ -0,0 +1,209 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::field_collider::{FieldContours, FilledField};
+
+use super::{
+    break_chain,
+    collider::{ChainCollider, Contours, ConvexPolygon, RoomBounds},
+    rope::draw_ropes,
+    simulate_chain, simulate_chains, Chain, ChainBroken, ChainParams,
+};
+
+/// simulates every entity with a `Chain`, and keeps their link sprites or ropes in sync with them.
+#[derive(Default)]
+pub struct ChainPlugin {
+    pub renderer: ChainRenderer,
+}
+
+/// how chains are drawn. The plugin adds this as a resource, so that whatever spawns
+/// chain entities can give them the matching components. Sprites and ropes are both kept
+/// in sync either way, so they can be mixed (e.g. a rope with a sprite on the last link).
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ChainRenderer {
+    /// a `ChainLinkSprite` for every link.
+    #[default]
+    Sprites,
+    /// a `Rope` through all the links, which needs lyon's `ShapePlugin` too.
+    Ropes,
+}
+
+/// the systems added by `ChainPlugin`, anything that moves pinned links should run before these.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChainSystems;
+
+impl Plugin for ChainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChainBroken>()
+            .insert_resource(self.renderer)
+            .add_systems(
+                FixedUpdate,
+                (
+                    simulate_chain_entities,
+                    break_chain_entities,
+                    sync_chain_link_sprites,
+                    draw_ropes,
+                )
+                    .chain()
+                    .in_set(ChainSystems),
+            );
+    }
+}
+
+/// keeps the links of the chain on this entity inside of the bounds.
+#[derive(Component, Clone, Copy, PartialEq)]
+pub struct ChainBounds(pub RoomBounds);
+
+/// every chain collides with the contours (or the `FilledField`) of entities that have this.
+#[derive(Component)]
+pub struct BlocksChains;
+
+/// a sprite that's stretched between a link and the one before it.
+#[derive(Component)]
+pub struct ChainLinkSprite {
+    pub chain: Entity,
+    pub link: usize,
+    /// length of the image along the chain, in pixels.
+    pub image_size: f32,
+    /// how much longer than the link the sprite is drawn, so that neighboring links overlap.
+    pub overlap: f32,
+}
+
+/// the bounds of a chain (if it has any), followed by everything that blocks every chain.
+fn chain_colliders<'a>(
+    bounds: Option<&'a ChainBounds>,
+    blocking: &[&'a dyn ChainCollider],
+) -> Vec<&'a dyn ChainCollider> {
+    bounds
+        .map(|bounds| &bounds.0 as &dyn ChainCollider)
+        .into_iter()
+        .chain(blocking.iter().copied())
+        .collect()
+}
+
+pub fn simulate_chain_entities(
+    time: Res<Time>,
+    mut chains: Query<(&mut Chain, Option<&ChainParams>, Option<&ChainBounds>)>,
+    solids: Query<(&FieldContours, Option<&FilledField>, &GlobalTransform), With<BlocksChains>>,
+) {
+    let contours = solids
+        .iter()
+        .map(|(contours, _, transform)| Contours::new(&contours.0, transform.translation().xy()))
+        .collect::<Vec<_>>();
+    let filled = solids
+        .iter()
+        .filter_map(|(_, filled, transform)| {
+            let rect = filled?.0?;
+            let offset = transform.translation().xy();
+            let points = [
+                rect.min,
+                Vec2::new(rect.max.x, rect.min.y),
+                rect.max,
+                Vec2::new(rect.min.x, rect.max.y),
+            ];
+            Some(ConvexPolygon {
+                points: points.map(|p| p + offset).to_vec(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let blocking = contours
+        .iter()
+        .map(|contours| contours as &dyn ChainCollider)
+        .chain(filled.iter().map(|filled| filled as &dyn ChainCollider))
+        .collect::<Vec<_>>();
+
+    // chains that collide with each other have to be stepped together, so that the collisions
+    // are solved every substep, which only works for chains with the same params and bounds.
+    let mut groups: Vec<(ChainParams, Option<ChainBounds>, Vec<&mut Chain>)> = Vec::new();
+    for (chain, params, bounds) in chains.iter_mut() {
+        let params = ChainParams {
+            dt: time.delta_seconds(),
+            ..params.cloned().unwrap_or_default()
+        };
+        if !params.chain_collision {
+            simulate_chain(chain.into_inner(), &chain_colliders(bounds, &blocking), &params);
+            continue;
+        }
+        let bounds = bounds.copied();
+        let group = groups.iter_mut().find(|(group_params, group_bounds, _)| {
+            *group_params == params && *group_bounds == bounds
+        });
+        match group {
+            Some((_, _, group)) => group.push(chain.into_inner()),
+            None => groups.push((params, bounds, vec![chain.into_inner()])),
+        }
+    }
+    for (params, bounds, mut group) in groups {
+        simulate_chains(&mut group, &chain_colliders(bounds.as_ref(), &blocking), &params);
+    }
+}
+
+/// breaks overstretched chains, the links that come off become a new chain entity
+/// with the same params and bounds.
+pub fn break_chain_entities(
+    mut commands: Commands,
+    mut chains: Query<(Entity, &mut Chain, Option<&ChainParams>, Option<&ChainBounds>)>,
+    mut broken: EventWriter<ChainBroken>,
+) {
+    for (entity, mut chain, params, bounds) in chains.iter_mut() {
+        let Some(piece) = break_chain(&mut chain) else {
+            continue;
+        };
+        let mut piece_entity = commands.spawn(piece);
+        if let Some(params) = params {
+            piece_entity.insert(params.clone());
+        }
+        if let Some(bounds) = bounds {
+            piece_entity.insert(*bounds);
+        }
+        broken.send(ChainBroken {
+            chain: entity,
+            link: chain.links.len(),
+            piece: piece_entity.id(),
+        });
+    }
+}
+
+/// moves the sprites of links that broke off over to the chain they're now a part of,
+/// and lines every sprite up with its link. The sprite of the link where a chain snapped
+/// is removed, since it has nothing to connect to.
+pub fn sync_chain_link_sprites(
+    mut commands: Commands,
+    mut broken: EventReader<ChainBroken>,
+    chains: Query<&Chain>,
+    mut sprites: Query<(Entity, &mut ChainLinkSprite, &mut Transform, &mut Visibility)>,
+) {
+    for event in broken.read() {
+        for (entity, mut sprite, _, _) in sprites.iter_mut() {
+            if sprite.chain != event.chain || sprite.link < event.link {
+                continue;
+            }
+            if sprite.link == event.link {
+                commands.entity(entity).despawn();
+            } else {
+                sprite.chain = event.piece;
+                sprite.link -= event.link;
+            }
+        }
+    }
+
+    for (_, sprite, mut transform, mut visibility) in sprites.iter_mut() {
+        let Ok(chain) = chains.get(sprite.chain) else {
+            continue;
+        };
+        if sprite.link == 0 || sprite.link >= chain.links.len() {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        let link = &chain.links[sprite.link];
+        let prev_loc = chain.links[sprite.link - 1].loc;
+        let angle = (link.loc - prev_loc).to_angle();
+        let loc = (link.loc + prev_loc) / 2.0;
+        let scale = link.len * sprite.overlap / sprite.image_size;
+        transform.scale = Vec3::new(scale, scale, 1.0);
+        transform.translation = loc.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(angle + PI / 2.0);
+        visibility.set_if_neq(Visibility::Visible);
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::{tess::math::point, *};
+
+use super::{Chain, ChainBroken};
+
+/// draws a chain as a smooth rope through its links, instead of a sprite per link.
+/// needs to be on an entity with a lyon `Path` and `Stroke`, see `RopeBundle`.
+#[derive(Component, Clone)]
+pub struct Rope {
+    pub chain: Entity,
+    /// width of the rope relative to the diameter of the links it passes through.
+    pub width: f32,
+    /// width of the rope at the last link, relative to its width at the first.
+    pub taper: f32,
+    /// how many points of the spline are placed between every pair of links.
+    pub samples_per_link: usize,
+}
+
+#[derive(Bundle)]
+pub struct RopeBundle {
+    pub rope: Rope,
+    pub shape: ShapeBundle,
+    pub stroke: Stroke,
+}
+
+impl RopeBundle {
+    pub fn new(chain: Entity, color: Color) -> Self {
+        Self {
+            rope: Rope {
+                chain,
+                width: 1.0,
+                taper: 1.0,
+                samples_per_link: 4,
+            },
+            shape: ShapeBundle::default(),
+            stroke: rope_stroke(color),
+        }
+    }
+}
+
+/// a stroke that reads the width of the rope from the path, so that it can taper.
+pub fn rope_stroke(color: Color) -> Stroke {
+    Stroke {
+        options: StrokeOptions::default()
+            .with_variable_line_width(0)
+            .with_line_join(LineJoin::Round)
+            .with_line_cap(LineCap::Round),
+        color,
+    }
+}
+
+/// point on the catmull-rom spline between p1 and p2, t goes from 0 at p1 to 1 at p2.
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// samples a catmull-rom spline that passes through every point.
+/// the first and last points are repeated so the spline reaches the ends.
+pub fn rope_spline(points: &[Vec2], samples_per_link: usize) -> Vec<Vec2> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+    let samples_per_link = samples_per_link.max(1);
+    let last = points.len() - 1;
+    let mut spline = Vec::with_capacity(last * samples_per_link + 1);
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let p3 = points[(i + 2).min(last)];
+        for sample in 0..samples_per_link {
+            let t = sample as f32 / samples_per_link as f32;
+            spline.push(catmull_rom(p0, points[i], points[i + 1], p3, t));
+        }
+    }
+    spline.push(points[last]);
+    spline
+}
+
+/// width of the rope at every point of `rope_spline`, going from the diameter of one link
+/// to the next and shrinking by `taper` along the whole rope.
+pub fn rope_widths(radii: &[f32], samples_per_link: usize, taper: f32) -> Vec<f32> {
+    if radii.len() < 2 {
+        return radii.iter().map(|radius| radius * 2.0).collect();
+    }
+    let samples_per_link = samples_per_link.max(1);
+    let last = radii.len() - 1;
+    (0..=last * samples_per_link)
+        .map(|sample| {
+            let i = (sample / samples_per_link).min(last - 1);
+            let t = (sample - i * samples_per_link) as f32 / samples_per_link as f32;
+            let along = sample as f32 / (last * samples_per_link) as f32;
+            let radius = radii[i] + (radii[i + 1] - radii[i]) * t;
+            radius * 2.0 * (1.0 + (taper - 1.0) * along)
+        })
+        .collect()
+}
+
+/// gives the pieces that break off of roped chains a rope of their own, and rebuilds
+/// the path of every rope from its chain.
+pub fn draw_ropes(
+    mut commands: Commands,
+    mut broken: EventReader<ChainBroken>,
+    chains: Query<&Chain>,
+    mut ropes: Query<(&Rope, &Stroke, &Transform, &mut Path)>,
+) {
+    for event in broken.read() {
+        for (rope, stroke, transform, _) in ropes.iter() {
+            if rope.chain != event.chain {
+                continue;
+            }
+            commands.spawn(RopeBundle {
+                rope: Rope {
+                    chain: event.piece,
+                    ..rope.clone()
+                },
+                shape: ShapeBundle {
+                    spatial: SpatialBundle::from_transform(*transform),
+                    ..default()
+                },
+                stroke: *stroke,
+            });
+        }
+    }
+
+    for (rope, _, _, mut path) in ropes.iter_mut() {
+        let Ok(chain) = chains.get(rope.chain) else {
+            *path = Path::default();
+            continue;
+        };
+        let points = chain.links.iter().map(|link| link.loc).collect::<Vec<_>>();
+        let radii = chain
+            .links
+            .iter()
+            .map(|link| link.radius * rope.width)
+            .collect::<Vec<_>>();
+        let spline = rope_spline(&points, rope.samples_per_link);
+        let widths = rope_widths(&radii, rope.samples_per_link, rope.taper);
+
+        let mut builder = tess::path::Path::builder_with_attributes(1);
+        if let Some((first, rest)) = spline.split_first() {
+            builder.begin(point(first.x, first.y), &[widths[0]]);
+            for (p, width) in rest.iter().zip(&widths[1..]) {
+                builder.line_to(point(p.x, p.y), &[*width]);
+            }
+            builder.end(false);
+        }
+        *path = Path(builder.build());
+    }
+}
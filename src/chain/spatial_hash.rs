@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use bevy::math::{IVec2, Vec2};
+
+/// buckets points into a grid of square cells, so that the points near a location
+/// can be found without checking every other point.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl SpatialHash {
+    /// points that are within `cell_size` of each other are always found by `nearby`.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, loc: Vec2) -> IVec2 {
+        (loc / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn insert(&mut self, loc: Vec2, id: usize) {
+        let cell = self.cell(loc);
+        self.cells.entry(cell).or_default().push(id);
+    }
+
+    /// ids of the points in the cells surrounding the location, which includes every point
+    /// within `cell_size` of it (along with some that are further away).
+    pub fn nearby(&self, loc: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let cell = self.cell(loc);
+        (-1..=1)
+            .flat_map(move |y| (-1..=1).map(move |x| cell + IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
@@ -1,83 +1,210 @@
 
 
-use std::ops::Add;
-
 use bevy::{prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}};
+use bevy_rapier2d::prelude::*;
+
+use crate::{droplet_config::{DropletConfig, DropletConfigHandle}, field_collider::{FieldColliderShape, FieldContours}, input::ActionState, marching_squares::{marching_squares_contours, marching_squares_indexed, matrix::Matrix, tiles::Tiles, Contour, MarchingSquaresSettings, SaddleResolution}, mesh::{set_mesh_attributes_according_to_indexed_verts, verts_to_mesh}, point::Point};
+
+/// metaballs add up to a density of 1 at their center, and the droplet's
+/// surface is where they add up to this.
+const METABALL_THRESHOLD: f32 = 0.5;
 
-use crate::{marching_squares::{marching_squares, matrix::Matrix, tiles::Tiles}, mesh::{set_mesh_attributes_according_to_verts, verts_to_mesh}, point::Point};
+/// the most densities the droplet grid can have along each axis. If the trail gets
+/// spread out further than this allows, the cells are made bigger instead.
+const MAX_DROPLET_RES: usize = 256;
 
 #[derive(Component)]
 pub struct Droplet {
     pub posns: Vec<(Vec2, f32)>,
-    pub max_posns_len: usize,
+    /// seconds left in the current dash, 0 when not dashing.
+    pub dash_timer: f32,
+    pub health: f32,
+}
+
+/// the outline of the droplet's trail, as a sensor. The droplet itself only
+/// collides with things using the ball around its head.
+#[derive(Component)]
+pub struct DropletSensor;
+
+/// hurts the droplet when it runs into this.
+#[derive(Component)]
+pub struct Hazard {
+    pub damage: f32,
+}
+
+/// distance from the center of a metaball at which it stops having any effect on the densities,
+/// `radius` is where the surface of the metaball would be if it were on its own.
+fn influence_radius(radius: f32) -> f32 {
+    radius / (1.0 - METABALL_THRESHOLD.cbrt()).sqrt()
 }
 
-/// Calculates droplet geometry using a grid-based marching squares approach
+/// subtracts a metaball from the densities, using the polynomial (1 - d²/R²)³ falloff which reaches
+/// exactly zero at the influence radius R, so only the densities within R need to be touched.
+/// `radius` is where the surface of the metaball would be if it were on its own,
+/// and `origin` is the location of the density at [0, 0].
+fn add_metaball(densities: &mut Matrix<f32, 2>, origin: Vec2, scale: f32, center: Vec2, radius: f32) {
+    let influence = influence_radius(radius);
+    let dim = densities.dim();
+    let min = ((center - influence - origin) / scale).ceil().max(Vec2::ZERO);
+    let max = ((center + influence - origin) / scale)
+        .floor()
+        .min(Vec2::new(dim[0] as f32 - 1.0, dim[1] as f32 - 1.0));
+    if min.x > max.x || min.y > max.y {
+        return;
+    }
+    for y in min.y as usize..=max.y as usize {
+        for x in min.x as usize..=max.x as usize {
+            let pos = origin + Vec2::new(x as f32, y as f32) * scale;
+            let falloff = 1.0 - pos.distance_squared(center) / (influence * influence);
+            if falloff > 0.0 {
+                *densities.get_mut([x, y]) -= falloff * falloff * falloff;
+            }
+        }
+    }
+}
+
+/// Calculates droplet geometry using a grid-based marching squares approach.
+/// The grid covers every metaball in the trail, and the geometry is relative to the first one.
 fn calculate_droplet_geometry(
     posns: &[(Vec2, f32)],
-    res: [usize; 2],
-    scale: f64,
+    cell_size: f32,
     radius: f32,
     decay: f32,
-) -> Vec<Point<f32, 2>> {
-    let mut elems = vec![0.0; res[0] * res[1]];
-    for y in 0..res[1] {
-        for x in 0..res[0] {
-            let idx = y * res[0] + x;
-            let elempos = Vec2::new(
-                x as f32 - res[0] as f32 / 2.0,
-                y as f32 - res[1] as f32 / 2.0,
-            ) * scale as f32;
-            let mut r = radius;
-            let mut sum = 0.0;
-            for (pos, f) in posns {
-                let diff = elempos - (*pos - posns[0].0);
-                sum += (1.0 - r * r * f * f / diff.length_squared()).max(-10000.0);
-                r *= decay;
-            }
-            elems[idx] = sum / posns.len() as f32;
+) -> (Vec<Point<f32, 2>>, Vec<u32>, Vec<Contour>) {
+    let Some(&(start, _)) = posns.first() else {
+        return (Vec::new(), Vec::new(), Vec::new());
+    };
+    let metaballs: Vec<_> = posns
+        .iter()
+        .scan(radius, |r, (pos, f)| {
+            let metaball = (*pos - start, *r * f);
+            *r *= decay;
+            Some(metaball)
+        })
+        .collect();
+
+    let (min, max) = metaballs.iter().fold(
+        (Vec2::INFINITY, Vec2::NEG_INFINITY),
+        |(min, max), (center, radius)| {
+            let influence = influence_radius(*radius);
+            (min.min(*center - influence), max.max(*center + influence))
+        },
+    );
+    let cell_size = cell_size.max((max - min).max_element() / (MAX_DROPLET_RES - 4) as f32);
+    // snap the grid to the world, so the densities don't shift around underneath
+    // the droplet as it moves, then pad it so the edges are always empty.
+    let origin = ((start + min) / cell_size).floor() * cell_size - start - cell_size;
+    let size = ((max - origin) / cell_size).ceil() + 2.0;
+    let res = [size.x as usize, size.y as usize];
+
+    let mut mat = Matrix::new(res, vec![METABALL_THRESHOLD; res[0] * res[1]]);
+    for (center, radius) in metaballs {
+        add_metaball(&mut mat, origin, cell_size, center, radius);
+    }
+    let tiles: Tiles<f32> = Tiles::new(&mat, cell_size as f64);
+    let settings = MarchingSquaresSettings {
+        // keeps trail blobs that almost touch from flickering between joined and separate
+        saddle: SaddleResolution::AsymptoticDecider,
+        ..Default::default()
+    };
+    let origin = Point::new([origin.x, origin.y]);
+    let (verts, indices) = marching_squares_indexed(&tiles, settings);
+    let verts = verts.into_iter().map(|v| v + origin).collect();
+    let mut contours = marching_squares_contours(&tiles, settings);
+    for contour in &mut contours {
+        for point in &mut contour.points {
+            *point = *point + origin;
         }
     }
-    let mat = Matrix::new(res, &elems);
-    let tiles: Tiles<f32> = Tiles::new(mat, scale);
-    let (verts, _) = marching_squares(&tiles);
-    verts
+    (verts, indices, contours)
 }
 
 pub fn move_droplet(
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
+    mut actions: ResMut<ActionState>,
+    config: Res<DropletConfigHandle>,
+    configs: Res<Assets<DropletConfig>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut droplet: Query<(&mut Droplet, &mut Transform, &Mesh2dHandle)>,
+    mut droplet: Query<(
+        &mut Droplet,
+        &mut Velocity,
+        &mut Collider,
+        &Transform,
+        &Mesh2dHandle,
+    )>,
+    mut sensor: Query<&mut FieldContours, With<DropletSensor>>,
 ) {
-    let dir = [
-        (KeyCode::KeyW, Vec2::Y),
-        (KeyCode::KeyA, -Vec2::X),
-        (KeyCode::KeyS, -Vec2::Y),
-        (KeyCode::KeyD, Vec2::X),
-    ]
-    .into_iter()
-    .filter(|(key, _)| keys.pressed(*key))
-    .map(|(_, dir)| dir)
-    .fold(Vec2::ZERO, Vec2::add)
-    .normalize_or_zero();
-
-    let (mut droplet, mut droplet_transform, droplet_mesh_handle) = droplet.single_mut();
+    let config = config.get(&configs);
+    let (mut droplet, mut velocity, mut collider, droplet_transform, droplet_mesh_handle) =
+        droplet.single_mut();
+    let dir = actions.move_axis;
+    let dt = time.delta_seconds();
+
+    droplet.dash_timer = (droplet.dash_timer - dt).max(0.0);
+    let dash_speed = config.speed * config.dash_speed_multiplier;
+    if actions.take_dash() && droplet.dash_timer == 0.0 && dir != Vec2::ZERO {
+        droplet.dash_timer = config.dash_duration;
+        velocity.linvel = dir.normalize() * dash_speed;
+    }
+    let max_speed = if droplet.dash_timer > 0.0 {
+        dash_speed
+    } else {
+        config.speed
+    };
+    // friction only kicks in once there's no input, so turning around doesn't feel sluggish
+    let rate = if dir == Vec2::ZERO {
+        config.friction
+    } else {
+        config.acceleration
+    };
+    let diff = dir * max_speed - velocity.linvel;
+    velocity.linvel += diff.clamp_length_max(rate * dt);
+
+    if collider.as_ball().map(|ball| ball.radius()) != Some(config.radius) {
+        *collider = Collider::ball(config.radius);
+    }
+
     droplet.posns.insert(
         0,
         (
-            droplet_transform.translation.xy().into(),
-            ((time.elapsed_seconds_f64() * 10.).sin() * 0.2 + 1.0) as f32,
+            droplet_transform.translation.xy(),
+            ((time.elapsed_seconds_f64() * config.wobble_frequency as f64).sin()
+                * config.wobble_amplitude as f64
+                + 1.0) as f32,
         ),
     );
-    if droplet.posns.len() > droplet.max_posns_len {
-        droplet.posns.pop();
-    }
-    droplet_transform.translation += Vec3::new(dir.x, dir.y, 0.0) * time.delta_seconds() * 150.0;
+    // the config can be reloaded with a shorter trail, so this may need to drop more than one
+    droplet.posns.truncate(config.max_posns_len);
 
-    let geom = calculate_droplet_geometry(&droplet.posns, [75, 75], 100. / 75., 20., 0.925);
+    let (verts, indices, contours) =
+        calculate_droplet_geometry(&droplet.posns, config.cell_size, config.radius, config.decay);
     if let Some(mesh) = meshes.get_mut(droplet_mesh_handle.0.id()) {
-        set_mesh_attributes_according_to_verts(mesh, &geom);
+        set_mesh_attributes_according_to_indexed_verts(mesh, &verts, &indices);
+    }
+    sensor.single_mut().0 = contours;
+}
+
+/// takes damage from anything that hurts the droplet when it starts touching it.
+pub fn droplet_contacts(
+    mut collisions: EventReader<CollisionEvent>,
+    mut droplet: Query<(Entity, &mut Droplet)>,
+    hazards: Query<&Hazard>,
+) {
+    let (droplet_entity, mut droplet) = droplet.single_mut();
+    for collision in collisions.read() {
+        let CollisionEvent::Started(a, b, _) = *collision else {
+            continue;
+        };
+        let other = if a == droplet_entity {
+            b
+        } else if b == droplet_entity {
+            a
+        } else {
+            continue;
+        };
+        if let Ok(hazard) = hazards.get(other) {
+            droplet.health -= hazard.damage;
+        }
     }
 }
 
@@ -88,15 +215,31 @@ pub fn setup_droplet(
 ) {
     let droplet = Droplet {
         posns: Vec::new(),
-        max_posns_len: 20,
+        dash_timer: 0.0,
+        health: 100.0,
     };
-    commands.spawn((
-        MaterialMesh2dBundle {
-            // start our droplet out with an empty mesh, it'll get updated next frame anyways
-            mesh: meshes.add(verts_to_mesh(&[])).into(),
-            material: materials.add(ColorMaterial::from(Color::BLACK)),
-            ..default()
-        },
-        droplet,
-    ));
-}
\ No newline at end of file
+    commands
+        .spawn((
+            MaterialMesh2dBundle {
+                // start our droplet out with an empty mesh, it'll get updated next frame anyways
+                mesh: meshes.add(verts_to_mesh(&[])).into(),
+                material: materials.add(ColorMaterial::from(Color::BLACK)),
+                ..default()
+            },
+            droplet,
+            RigidBody::Dynamic,
+            Velocity::zero(),
+            Collider::ball(DropletConfig::default().radius),
+            LockedAxes::ROTATION_LOCKED,
+            ActiveEvents::COLLISION_EVENTS,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                DropletSensor,
+                FieldContours::default(),
+                FieldColliderShape::Polyline,
+                Sensor,
+                TransformBundle::default(),
+            ));
+        });
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+/// everything that decides how the droplet looks and feels, loaded from a `.droplet.ron` file
+/// so it can be tuned (and hot reloaded) without recompiling.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct DropletConfig {
+    /// radius of the metaball at the head of the droplet.
+    pub radius: f32,
+    /// how much smaller every metaball in the trail is than the one before it.
+    pub decay: f32,
+    /// size of the cells the droplet is meshed with, smaller is smoother.
+    pub cell_size: f32,
+    /// fastest the droplet can move on its own, in pixels per second.
+    /// it can still go faster than this when it gets knocked around.
+    pub speed: f32,
+    /// how quickly the droplet speeds up towards the direction it's moving in,
+    /// in pixels per second squared.
+    pub acceleration: f32,
+    /// how quickly the droplet slows down once it stops moving, in pixels per second squared.
+    pub friction: f32,
+    /// how many times faster the droplet moves while dashing.
+    pub dash_speed_multiplier: f32,
+    /// how long a dash lasts, in seconds.
+    pub dash_duration: f32,
+    /// how fast the head of the droplet wobbles, in radians per second.
+    pub wobble_frequency: f32,
+    /// how much the head of the droplet grows and shrinks as it wobbles, relative to its radius.
+    pub wobble_amplitude: f32,
+    /// number of metaballs in the trail.
+    pub max_posns_len: usize,
+}
+
+impl Default for DropletConfig {
+    fn default() -> Self {
+        Self {
+            radius: 20.0,
+            decay: 0.925,
+            cell_size: 100.0 / 75.0,
+            speed: 150.0,
+            acceleration: 1200.0,
+            friction: 900.0,
+            dash_speed_multiplier: 3.0,
+            dash_duration: 0.15,
+            wobble_frequency: 10.0,
+            wobble_amplitude: 0.2,
+            max_posns_len: 20,
+        }
+    }
+}
+
+/// the config the droplet is currently using. Falls back to the default config
+/// until the file is loaded (or if it fails to load).
+#[derive(Resource)]
+pub struct DropletConfigHandle(pub Handle<DropletConfig>);
+
+impl DropletConfigHandle {
+    pub fn get(&self, configs: &Assets<DropletConfig>) -> DropletConfig {
+        configs.get(&self.0).cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub enum DropletConfigLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for DropletConfigLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read droplet config: {err}"),
+            Self::Ron(err) => write!(f, "could not parse droplet config: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DropletConfigLoaderError {}
+
+impl From<std::io::Error> for DropletConfigLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for DropletConfigLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+#[derive(Default)]
+pub struct DropletConfigLoader;
+
+impl AssetLoader for DropletConfigLoader {
+    type Asset = DropletConfig;
+    type Settings = ();
+    type Error = DropletConfigLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<DropletConfig, DropletConfigLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["droplet.ron"]
+    }
+}
+
+pub fn load_droplet_config(mut commands: Commands, server: Res<AssetServer>) {
+    commands.insert_resource(DropletConfigHandle(server.load("default.droplet.ron")));
+}
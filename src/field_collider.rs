@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::marching_squares::Contour;
+
+/// how the outlines of a marching squares field get turned into a collider.
+#[derive(Component, Clone, Copy, Default)]
+pub enum FieldColliderShape {
+    /// hollow outlines, cheap enough to rebuild every frame.
+    #[default]
+    Polyline,
+    /// solid convex pieces, expensive to build, so better suited to fields that rarely change.
+    ConvexDecomposition,
+}
+
+/// outlines of a marching squares field, in the local space of the entity they're attached to.
+/// whenever these change, the entity's collider gets rebuilt from them.
+#[derive(Component, Default)]
+pub struct FieldContours(pub Vec<Contour>);
+
+/// a field that's filled in everywhere has no contours, so this is the area (in the local space
+/// of the entity) that it fills, to still give it something to collide with.
+#[derive(Component, Default)]
+pub struct FilledField(pub Option<Rect>);
+
+/// builds a collider out of the provided contours, returns None if there's nothing to collide with.
+pub fn contours_to_collider(contours: &[Contour], shape: FieldColliderShape) -> Option<Collider> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for contour in contours {
+        let start = vertices.len() as u32;
+        vertices.extend(contour.points.iter().map(|p| Vec2::new(p[0], p[1])));
+        let end = vertices.len() as u32;
+        indices.extend((start..end - 1).map(|i| [i, i + 1]));
+        // convex decomposition needs closed outlines, so outlines that run off the edge
+        // of the field get closed off with a straight line.
+        if contour.closed || matches!(shape, FieldColliderShape::ConvexDecomposition) {
+            indices.push([end - 1, start]);
+        }
+    }
+    if indices.is_empty() {
+        return None;
+    }
+    Some(match shape {
+        FieldColliderShape::Polyline => Collider::polyline(vertices, Some(indices)),
+        FieldColliderShape::ConvexDecomposition => {
+            Collider::convex_decomposition(&vertices, &indices)
+        }
+    })
+}
+
+pub fn update_field_colliders(
+    mut commands: Commands,
+    fields: Query<
+        (Entity, &FieldContours, &FieldColliderShape, Option<&FilledField>),
+        Or<(Changed<FieldContours>, Changed<FilledField>)>,
+    >,
+) {
+    for (entity, FieldContours(contours), shape, filled) in fields.iter() {
+        let filled = filled.and_then(|filled| filled.0).map(|rect| {
+            let half_size = rect.half_size();
+            Collider::compound(vec![(
+                rect.center(),
+                0.0,
+                Collider::cuboid(half_size.x, half_size.y),
+            )])
+        });
+        match contours_to_collider(contours, *shape).or(filled) {
+            Some(collider) => {
+                commands.entity(entity).insert(collider);
+            }
+            None => {
+                commands.entity(entity).remove::<Collider>();
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// where the bindings are read from on startup, and written back to when they're changed.
+pub const BINDINGS_PATH: &str = "input.ron";
+
+/// which analog stick of the gamepad moves the player.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    fn axes(self) -> (GamepadAxisType, GamepadAxisType) {
+        match self {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+/// maps keys and gamepad buttons to actions, every action can have any number of bindings.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct InputBindings {
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub dash: Vec<KeyCode>,
+    pub attack: Vec<KeyCode>,
+    pub move_stick: Stick,
+    /// how far the stick has to be pushed before it counts as moving, from 0 to 1.
+    /// applied to the length of the stick's position, so diagonals aren't harder to reach.
+    pub stick_deadzone: f32,
+    pub gamepad_dash: Vec<GamepadButtonType>,
+    pub gamepad_attack: Vec<GamepadButtonType>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            up: vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            down: vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            left: vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            right: vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            dash: vec![KeyCode::Space, KeyCode::ShiftLeft],
+            attack: vec![KeyCode::KeyJ, KeyCode::KeyZ],
+            move_stick: Stick::Left,
+            stick_deadzone: 0.2,
+            gamepad_dash: vec![GamepadButtonType::South, GamepadButtonType::RightTrigger],
+            gamepad_attack: vec![GamepadButtonType::West],
+        }
+    }
+}
+
+impl InputBindings {
+    /// reads the bindings from the provided file, falling back to the defaults
+    /// if it doesn't exist or can't be parsed.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path).map(|text| ron::from_str(&text)) {
+            Ok(Ok(bindings)) => bindings,
+            Ok(Err(err)) => {
+                warn!("could not parse input bindings {}: {err}", path.display());
+                Self::default()
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                warn!("could not read input bindings {}: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// writes the bindings to the provided file, so that rebinding persists between runs.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
+
+/// what the player is currently asking for, regardless of what they're asking with.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    /// direction to move in, with a length of at most 1.
+    /// analog sticks can give lengths in between 0 and 1.
+    pub move_axis: Vec2,
+    /// true while any attack binding is held, hurts anchor boy while the droplet is close to it.
+    pub attack: bool,
+    dash: bool,
+}
+
+impl ActionState {
+    /// returns true if dash has been pressed since the last time this was called.
+    /// presses are kept around until they're taken, so they aren't lost if
+    /// the fixed timestep doesn't run on the frame the button was pressed.
+    pub fn take_dash(&mut self) -> bool {
+        std::mem::take(&mut self.dash)
+    }
+}
+
+/// scales the stick position so that it's 0 inside the deadzone,
+/// and goes smoothly from 0 to 1 between the deadzone and the edge.
+fn apply_radial_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let len = stick.length();
+    if len <= deadzone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((len - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick / len * scaled
+}
+
+pub fn load_input_bindings(mut commands: Commands) {
+    let bindings = InputBindings::load(BINDINGS_PATH);
+    // write the defaults out on the first run, so there's a file to rebind things in
+    if !Path::new(BINDINGS_PATH).exists() {
+        if let Err(err) = bindings.save(BINDINGS_PATH) {
+            warn!("could not write input bindings {BINDINGS_PATH}: {err}");
+        }
+    }
+    commands.insert_resource(bindings);
+}
+
+pub fn update_action_state(
+    bindings: Res<InputBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<ActionState>,
+) {
+    let key_axis = [
+        (&bindings.up, Vec2::Y),
+        (&bindings.down, -Vec2::Y),
+        (&bindings.left, -Vec2::X),
+        (&bindings.right, Vec2::X),
+    ]
+    .into_iter()
+    .filter(|(keys_for_dir, _)| keys.any_pressed(keys_for_dir.iter().copied()))
+    .map(|(_, dir)| dir)
+    .sum::<Vec2>()
+    .normalize_or_zero();
+
+    let (x_axis, y_axis) = bindings.move_stick.axes();
+    let stick_axis = gamepads
+        .iter()
+        .map(|gamepad| {
+            let x = gamepad_axes.get(GamepadAxis::new(gamepad, x_axis)).unwrap_or(0.0);
+            let y = gamepad_axes.get(GamepadAxis::new(gamepad, y_axis)).unwrap_or(0.0);
+            apply_radial_deadzone(Vec2::new(x, y), bindings.stick_deadzone)
+        })
+        .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .unwrap_or(Vec2::ZERO);
+
+    // keys win over the stick, so a resting controller can't get in the way of the keyboard
+    actions.move_axis = if key_axis != Vec2::ZERO {
+        key_axis
+    } else {
+        stick_axis
+    };
+
+    let gamepad_pressed = |buttons: &[GamepadButtonType], just: bool| {
+        gamepads.iter().any(|gamepad| {
+            buttons.iter().any(|button| {
+                let button = GamepadButton::new(gamepad, *button);
+                if just {
+                    gamepad_buttons.just_pressed(button)
+                } else {
+                    gamepad_buttons.pressed(button)
+                }
+            })
+        })
+    };
+    actions.dash |= keys.any_just_pressed(bindings.dash.iter().copied())
+        || gamepad_pressed(&bindings.gamepad_dash, true);
+    actions.attack = keys.any_pressed(bindings.attack.iter().copied())
+        || gamepad_pressed(&bindings.gamepad_attack, false);
+}
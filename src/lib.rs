@@ -0,0 +1,11 @@
+pub mod anchorboy;
+pub mod chain;
+pub mod droplet;
+pub mod droplet_config;
+pub mod field_collider;
+pub mod input;
+pub mod marching_squares;
+pub mod mesh;
+pub mod point;
+pub mod surface_nets;
+pub mod terrain;
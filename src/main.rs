@@ -1,27 +1,58 @@
-use anchorboy::{anchor_boy, set_angle_according_to_spin, set_link_properties, setup_anchor_boy, snap_links_to_chains};
-use bevy::prelude::*;
-use droplet::{move_droplet, setup_droplet};
-
-mod anchorboy;
-mod chain;
-mod droplet;
-mod marching_squares;
-mod mesh;
-mod point;
+use bevy::{input::InputSystem, prelude::*};
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use perilous::{
+    anchorboy::{anchor_boy, set_angle_according_to_spin, setup_anchor_boy},
+    chain::plugin::{ChainPlugin, ChainRenderer, ChainSystems},
+    droplet::{droplet_contacts, move_droplet, setup_droplet},
+    droplet_config::{load_droplet_config, DropletConfig, DropletConfigLoader},
+    field_collider::update_field_colliders,
+    input::{load_input_bindings, update_action_state, ActionState},
+    terrain::{apply_terrain_edits, remesh_terrain_chunks, setup_terrain, terrain_brush, TerrainEdit},
+};
 
 fn main() {
     App::new()
         .insert_resource(Msaa::Off)
         .insert_resource(ClearColor(Color::rgb(0.75, 0.7, 0.75)))
         .add_plugins(DefaultPlugins)
-        .add_systems(Startup, (setup_camera, setup_droplet, setup_anchor_boy))
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugins(ShapePlugin)
+        .add_plugins(ChainPlugin {
+            renderer: ChainRenderer::Sprites,
+        })
+        .init_asset::<DropletConfig>()
+        .init_asset_loader::<DropletConfigLoader>()
+        .init_resource::<ActionState>()
+        .add_event::<TerrainEdit>()
+        .add_systems(
+            Startup,
+            (
+                setup_camera,
+                setup_physics,
+                load_droplet_config,
+                load_input_bindings,
+                setup_droplet,
+                setup_anchor_boy,
+                setup_terrain,
+            ),
+        )
+        .add_systems(
+            PreUpdate,
+            update_action_state.after(InputSystem),
+        )
         .add_systems(
             FixedUpdate,
             (
                 move_droplet,
-                snap_links_to_chains,
-                anchor_boy,
-                set_link_properties,
+                droplet_contacts,
+                terrain_brush,
+                apply_terrain_edits.after(terrain_brush),
+                remesh_terrain_chunks.after(apply_terrain_edits),
+                update_field_colliders
+                    .after(move_droplet)
+                    .after(remesh_terrain_chunks),
+                anchor_boy.before(ChainSystems),
                 set_angle_according_to_spin,
             ),
         )
@@ -31,3 +62,8 @@ fn main() {
 fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
+
+fn setup_physics(mut config: ResMut<RapierConfiguration>) {
+    // everything is viewed from the top down, so nothing should fall.
+    config.gravity = Vec2::ZERO;
+}
@@ -1,20 +1,27 @@
+use std::marker::PhantomData;
+
 /// generic N dimensional matrix that can contain any types implementing
-/// the Copy and Default traits.
-pub struct Matrix<'a, T: Copy, const N: usize> {
+/// the Copy trait. The elements are stored in `S`, which is owned (`Vec<T>`) by default,
+/// but can also be borrowed (e.g. `&[T]`).
+pub struct Matrix<T: Copy, const N: usize, S = Vec<T>> {
     dim: [usize; N],
-    elems: &'a [T],
+    elems: S,
+    phantom: PhantomData<T>,
 }
 
-impl<'a, T: Copy, const N: usize> Matrix<'a, T, N> {
+impl<T: Copy, const N: usize, S: AsRef<[T]>> Matrix<T, N, S> {
     /// construct a new matrix with the provided dimensionality.
-    pub fn new(dim: [usize; N], elems: &'a [T]) -> Self {
-        let size = dim.iter().fold(1, |acc, elem| acc * elem);
-        if size != elems.len() {
+    pub fn new(dim: [usize; N], elems: S) -> Self {
+        if size(dim) != elems.as_ref().len() {
             panic!(
                 "unexpected size for elems, should be same as all dimensions multiplied together"
             );
         }
-        Self { dim, elems }
+        Self {
+            dim,
+            elems,
+            phantom: PhantomData,
+        }
     }
 
     fn index(&self, loc: [usize; N]) -> usize {
@@ -31,11 +38,146 @@ impl<'a, T: Copy, const N: usize> Matrix<'a, T, N> {
     /// you must ensure that the location is within the bounds,
     /// otherwise the function may return the wrong T, or panic.
     pub fn get(&self, loc: [usize; N]) -> T {
-        self.elems[self.index(loc)]
+        self.elems.as_ref()[self.index(loc)]
+    }
+
+    /// get the element at the provided location, or None if the location is out of bounds.
+    pub fn try_get(&self, loc: [usize; N]) -> Option<T> {
+        self.in_bounds(loc).then(|| self.get(loc))
+    }
+
+    /// returns true if every coordinate of the location is within the dimensions of the Matrix
+    pub fn in_bounds(&self, loc: [usize; N]) -> bool {
+        loc.iter().zip(self.dim).all(|(x, dim)| *x < dim)
     }
 
     /// returns the dimensions of the Matrix
     pub fn dim(&self) -> [usize; N] {
         self.dim
     }
+
+    /// iterates over all the elements, with the first dimension changing fastest.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.elems.as_ref().iter().copied()
+    }
+
+    /// same as `iter`, but also provides the location of every element.
+    pub fn indexed_iter(&self) -> impl Iterator<Item = ([usize; N], T)> + '_ {
+        let dim = self.dim;
+        self.iter().enumerate().map(move |(i, elem)| (location(dim, i), elem))
+    }
+
+    /// iterates over the elements along the provided axis that pass through the provided location.
+    /// the coordinate of the location along the axis is ignored.
+    pub fn axis_iter(&self, axis: usize, loc: [usize; N]) -> impl Iterator<Item = T> + '_ {
+        (0..self.dim[axis]).map(move |i| {
+            let mut loc = loc;
+            loc[axis] = i;
+            self.get(loc)
+        })
+    }
+
+    /// creates a new owned Matrix with the same dimensions, by applying the function to every element.
+    pub fn map<U: Copy>(&self, f: impl FnMut(T) -> U) -> Matrix<U, N> {
+        Matrix::new(self.dim, self.iter().map(f).collect())
+    }
+
+    /// borrows the elements of this Matrix, without copying them.
+    pub fn view(&self) -> Matrix<T, N, &[T]> {
+        Matrix::new(self.dim, self.elems.as_ref())
+    }
+}
+
+impl<T: Copy, const N: usize, S: AsRef<[T]> + AsMut<[T]>> Matrix<T, N, S> {
+    /// get a mutable reference to the element at the provided location.
+    /// you must ensure that the location is within the bounds,
+    /// otherwise the function may return the wrong T, or panic.
+    pub fn get_mut(&mut self, loc: [usize; N]) -> &mut T {
+        let index = self.index(loc);
+        &mut self.elems.as_mut()[index]
+    }
+
+    /// set the element at the provided location, see `get_mut`.
+    pub fn set(&mut self, loc: [usize; N], value: T) {
+        *self.get_mut(loc) = value;
+    }
+
+    /// set every element to the provided value.
+    pub fn fill(&mut self, value: T) {
+        self.elems.as_mut().fill(value);
+    }
+}
+
+impl<T: Copy, const N: usize> Matrix<T, N> {
+    /// construct a new matrix with the provided dimensionality,
+    /// calling the function with the location of every element to get its value.
+    pub fn from_fn(dim: [usize; N], mut f: impl FnMut([usize; N]) -> T) -> Self {
+        Self::new(dim, (0..size(dim)).map(|i| f(location(dim, i))).collect())
+    }
+}
+
+impl<T: Copy, S: AsRef<[T]>> Matrix<T, 2, S> {
+    /// iterates over the rows (elements with the same second coordinate) of the Matrix.
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // there are no elements if the rows are empty, so any chunk size gives no rows
+        self.elems.as_ref().chunks(self.dim[0].max(1))
+    }
+}
+
+/// number of elements in a Matrix with the provided dimensions.
+fn size<const N: usize>(dim: [usize; N]) -> usize {
+    dim.iter().product()
+}
+
+/// inverse of `Matrix::index`, converts an index into the elements back into a location.
+fn location<const N: usize>(dim: [usize; N], mut index: usize) -> [usize; N] {
+    let mut loc = [0; N];
+    for i in 0..N {
+        loc[i] = index % dim[i];
+        index /= dim[i];
+    }
+    loc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_fn_and_get() {
+        let matrix = Matrix::from_fn([3, 2], |[x, y]| x + 10 * y);
+        assert_eq!(matrix.iter().collect::<Vec<_>>(), [0, 1, 2, 10, 11, 12]);
+        assert_eq!(matrix.get([2, 1]), 12);
+        assert_eq!(matrix.try_get([2, 1]), Some(12));
+        assert_eq!(matrix.try_get([3, 0]), None);
+        assert_eq!(matrix.try_get([0, 2]), None);
+    }
+
+    #[test]
+    fn set_and_get_mut() {
+        let mut matrix = Matrix::new([2, 2], vec![0; 4]);
+        matrix.set([1, 0], 5);
+        *matrix.get_mut([0, 1]) += 7;
+        assert_eq!(matrix.iter().collect::<Vec<_>>(), [0, 5, 7, 0]);
+        matrix.fill(1);
+        assert!(matrix.iter().all(|x| x == 1));
+    }
+
+    #[test]
+    fn axis_iter_and_map() {
+        let matrix = Matrix::from_fn([3, 2, 2], |[x, y, z]| x + 10 * y + 100 * z);
+        assert_eq!(matrix.axis_iter(0, [0, 1, 1]).collect::<Vec<_>>(), [110, 111, 112]);
+        assert_eq!(matrix.axis_iter(2, [2, 0, 1]).collect::<Vec<_>>(), [2, 102]);
+        let mapped = matrix.map(|x| x as f32 / 2.0);
+        assert_eq!(mapped.dim(), [3, 2, 2]);
+        assert_eq!(mapped.get([1, 1, 1]), 55.5);
+    }
+
+    #[test]
+    fn rows() {
+        let matrix = Matrix::from_fn([2, 3], |[x, y]| x + 10 * y);
+        assert_eq!(matrix.rows().collect::<Vec<_>>(), [[0, 1], [10, 11], [20, 21]]);
+        let empty = Matrix::<i32, 2>::from_fn([0, 3], |_| 0);
+        assert_eq!(empty.rows().count(), 0);
+    }
 }
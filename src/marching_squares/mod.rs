@@ -1,12 +1,14 @@
 
-use std::fmt::Debug;
+use std::collections::{HashMap, HashSet};
 
+use itertools::Itertools;
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
 use lazy_static::lazy_static;
-use num_traits::{One, Signed, Zero};
+use num_traits::Float;
 
-use crate::point::{Numeric, Point};
+use crate::point::Point;
 
-use self::tiles::Tiles;
+use self::{matrix::Matrix, tiles::Tiles};
 
 pub mod matrix;
 pub mod tiles;
@@ -39,9 +41,12 @@ lazy_static! {
     ///   |         |
     ///   0----1----2
     ///
-    static ref TRIANGLE_MAPPINGS: [[Vec<usize>; 16]; 2] = {
+    /// map ids 16 and 17 are the saddles 5 and 10 with their filled corners
+    /// separated instead of connected through the middle of the tile.
+    ///
+    static ref TRIANGLE_MAPPINGS: [[Vec<usize>; 18]; 2] = {
         [
-            // default ruleset, use if there are any negative densities
+            // default ruleset, use if there are any densities below the iso level
             [
                 vec![],
                 vec![7, 1, 0],
@@ -59,8 +64,10 @@ lazy_static! {
                 vec![6, 4, 3, 6, 3, 1, 6, 1, 0],
                 vec![6, 4, 7, 4, 1, 7, 4, 2, 1],
                 vec![0, 4, 2, 0, 6, 4],
+                vec![7, 1, 0, 5, 4, 3],
+                vec![3, 2, 1, 7, 6, 5],
             ],
-            // zero and positive density only ruleset
+            // densities at or above the iso level only ruleset
             [
                 vec![],
                 vec![],
@@ -78,9 +85,133 @@ lazy_static! {
                 vec![],
                 vec![],
                 vec![0, 4, 2, 0, 6, 4],
+                vec![],
+                vec![],
             ]
         ]
     };
+
+    ///
+    /// tile points layout is the same as `TRIANGLE_MAPPINGS`.
+    /// every pair of points is a directed outline segment, oriented so that
+    /// the filled part of the tile is always to the left of the segment.
+    ///
+    static ref EDGE_MAPPINGS: [[Vec<usize>; 18]; 2] = {
+        [
+            // default ruleset, use if there are any densities below the iso level
+            [
+                vec![],
+                vec![1, 7],
+                vec![3, 1],
+                vec![3, 7],
+                vec![5, 3],
+                vec![1, 3, 5, 7],
+                vec![5, 1],
+                vec![5, 7],
+                vec![7, 5],
+                vec![1, 5],
+                vec![7, 1, 3, 5],
+                vec![3, 5],
+                vec![7, 3],
+                vec![1, 3],
+                vec![7, 1],
+                vec![],
+                vec![1, 7, 5, 3],
+                vec![3, 1, 7, 5],
+            ],
+            // densities at or above the iso level only ruleset
+            [
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+            ]
+        ]
+    };
+}
+
+/// trait to encompass the density types marching squares can be run over.
+pub trait Density: Float + Default + Send + Sync {}
+
+/// implement Density for all T satisfying the bounds.
+impl<T: Float + Default + Send + Sync> Density for T {}
+
+/// identifies a tile point, see `get_vertex_key`.
+type VertexKey = [Point<i32, 2>; 2];
+
+/// an ordered outline of a filled region produced by `marching_squares_contours`.
+/// points wind counter clockwise around filled regions and clockwise around holes,
+/// so the filled region is always to the left of the outline.
+#[derive(Debug, Clone)]
+pub struct Contour {
+    pub points: Vec<Point<f32, 2>>,
+    /// false if the outline runs off the edge of the tiles, in which case
+    /// the last point does not connect back to the first.
+    pub closed: bool,
+    /// true if this outline is the inner boundary of a filled region.
+    pub hole: bool,
+}
+
+/// how to pick the topology of saddle tiles, where only diagonally opposite corners are filled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaddleResolution {
+    /// always connect the filled corners through the middle of the tile.
+    #[default]
+    Connected,
+    /// connect the filled corners if the bilinear interpolation of the corner densities
+    /// is filled at its saddle point, which matches the interpolated outlines exactly.
+    AsymptoticDecider,
+    /// connect the filled corners if the average of the corner densities is filled.
+    CenterSample,
+}
+
+/// settings shared by all the marching squares functions.
+#[derive(Debug, Clone, Copy)]
+pub struct MarchingSquaresSettings<T> {
+    /// the density the outlines are drawn at. Densities at or below it are filled,
+    /// densities above it are empty.
+    pub iso: T,
+    pub saddle: SaddleResolution,
+    /// split the work up by rows across the compute task pool. Only worth it for large tiles,
+    /// the output is the same either way.
+    pub parallel: bool,
+}
+
+impl<T: Float> Default for MarchingSquaresSettings<T> {
+    fn default() -> Self {
+        Self {
+            iso: T::zero(),
+            saddle: SaddleResolution::default(),
+            parallel: false,
+        }
+    }
+}
+
+/// decides whether the filled corners of a saddle tile with the provided corner densities
+/// (relative to the iso level) should be connected through the middle of the tile.
+fn is_saddle_connected<T: Float>(densities: [T; 4], saddle: SaddleResolution) -> bool {
+    let [a, b, c, d] = densities;
+    match saddle {
+        SaddleResolution::Connected => true,
+        // the denominator can't be zero, since a and c are on the opposite side of the
+        // iso level from b and d for both saddles.
+        SaddleResolution::AsymptoticDecider => (a * c - b * d) / (a + c - b - d) <= T::zero(),
+        SaddleResolution::CenterSample => a + b + c + d <= T::zero(),
+    }
 }
 
 /// for marching squares, each combination of densities creates a different combination of triangles
@@ -88,17 +219,25 @@ lazy_static! {
 /// and finds the correct ruleset and map index within that ruleset to use to get the correct edge and triangle data.
 /// Use the returned (ruleset, map_id) tuple to index `TRIANGLE_MAPPINGS` and `EDGE_MAPPINGS` for the triangle
 /// and edge data respectively (e.g. `TRIANGLE_MAPPINGS[ruleset][map_id]`).
-fn get_ruleset_and_map_id<T : Default + Numeric<T> + Zero + One + Signed + Debug>(loc: Point<i32, 2>, tiles: &Tiles<T>) -> (usize, usize) {
+fn get_ruleset_and_map_id<T: Density>(
+    loc: Point<i32, 2>,
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> (usize, usize) {
     let positions = CORNERS_POINT.map(|x| loc + x);
-    let densities = positions.map(|x| tiles.get(x));
+    let densities = positions.map(|x| tiles.get(x) - settings.iso);
 
-    let ruleset = densities
-        .iter()
-        .all(|x| x.is_positive() || x.is_zero()) as usize;
+    let ruleset = densities.iter().all(|x| *x >= T::zero()) as usize;
     let map_id = densities.iter().enumerate().fold(0, |val, (i, density)| {
-        val + ((!density.is_positive() as usize) << i)
+        val + (((*density <= T::zero()) as usize) << i)
     });
-    
+
+    let map_id = match map_id {
+        5 if !is_saddle_connected(densities, settings.saddle) => 16,
+        10 if !is_saddle_connected(densities, settings.saddle) => 17,
+        _ => map_id,
+    };
+
     (ruleset, map_id)
 }
 
@@ -106,20 +245,21 @@ fn get_ruleset_and_map_id<T : Default + Numeric<T> + Zero + One + Signed + Debug
 /// need to use for marching squares. This point can be represented with a single f64 value
 /// that we return, which is the interpolation float between the two points.
 /// (We use this value to lerp between the first and second points).
-fn get_density_proportion(
+fn get_density_proportion<T: Density>(
     loc: Point<i32, 2>,
     corner_indices: [usize; 2],
-    tiles: &Tiles<f32>,
+    tiles: &Tiles<T>,
+    iso: T,
 ) -> f64 {
     let tile_loc = corner_indices.map(|x| loc + CORNERS_POINT[x]);
-    let densities = tile_loc.map(|x| tiles.get(x));
+    let densities = tile_loc.map(|x| tiles.get(x) - iso);
     let diff = densities[0] - densities[1];
     if densities[1].is_zero() {
         1.0
     } else if diff.is_zero() {
         0.0
     } else {
-        (densities[0] as f64) / (diff as f64)
+        (densities[0] / diff).to_f64().unwrap_or(0.0)
     }
 }
 
@@ -132,40 +272,401 @@ fn index_to_corner_indices(index: usize) -> [usize; 2] {
     [index / 2, ((index + 1) / 2) % 4]
 }
 
-pub fn marching_squares(tiles: &Tiles<f32>) -> (Vec<Point<f32, 2>>, Vec<Point<f32, 2>>) {
-    let mut collision_vertices = Vec::new();
-    let mut vertices = Vec::new();
-    for y in 0..(tiles.dimension()[1] - 1) as i32 {
-        for x in 0..(tiles.dimension()[0] - 1) as i32 {
-            let loc = [x, y].into();
-            let (ruleset, map_id) = get_ruleset_and_map_id(loc, tiles);
-            let tile_location: Point<f64, 2> = (loc,).into();
-            let tile_location = tile_location * tiles.dist_between_nodes();
+/// gets the location of one of the 8 tile points (see `TRIANGLE_MAPPINGS`) of the tile at the provided location,
+/// interpolated according to the densities of the corners it lies between.
+fn get_vertex_location<T: Density>(
+    loc: Point<i32, 2>,
+    corner_indices: [usize; 2],
+    tiles: &Tiles<T>,
+    iso: T,
+) -> Point<f32, 2> {
+    let tile_location: Point<f64, 2> = (loc,).into();
+    let tile_location = tile_location * tiles.dist_between_nodes();
+    let prop = get_density_proportion(loc, corner_indices, tiles, iso);
+    let rel_loc = CORNERS[corner_indices[0]].lerp(CORNERS[corner_indices[1]], prop);
+    let l = rel_loc * tiles.dist_between_nodes() + tile_location;
+    [l[0] as f32, l[1] as f32].into()
+}
+
+/// every tile point lies either on a grid node or on the edge between two grid nodes,
+/// so it can be uniquely identified by the (sorted) pair of grid nodes it lies between.
+/// neighboring tiles produce the same key for the points they share.
+fn get_vertex_key(loc: Point<i32, 2>, corner_indices: [usize; 2]) -> VertexKey {
+    let mut key = corner_indices.map(|x| loc + CORNERS_POINT[x]);
+    key.sort();
+    key
+}
+
+/// calls the function for every row of tiles, splitting the rows up across the compute
+/// task pool if `parallel` is set. Results are always returned in row order.
+fn map_rows<R: Send + 'static>(
+    rows: usize,
+    parallel: bool,
+    f: impl Fn(i32) -> R + Send + Sync,
+) -> Vec<R> {
+    let rows: Vec<i32> = (0..rows as i32).collect();
+    if !parallel {
+        return rows.into_iter().map(f).collect();
+    }
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    rows.par_splat_map(pool, None, |chunk| chunk.iter().map(|y| f(*y)).collect::<Vec<_>>())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// finds the (ruleset, map_id) of every tile up front, since
+/// each tile gets looked up several times by its neighbors.
+fn classify_tiles<T: Density>(
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> Matrix<(usize, usize), 2> {
+    let dim = tiles.tile_dimension();
+    let origin = tiles.tile_origin();
+    let cases = map_rows(dim[1], settings.parallel, |y| {
+        (0..dim[0] as i32)
+            .map(|x| get_ruleset_and_map_id(origin + [x, y].into(), tiles, settings))
+            .collect::<Vec<_>>()
+    });
+    Matrix::new(dim, cases.into_iter().flatten().collect())
+}
+
+/// looks up the (ruleset, map_id) of a tile from the classified tiles,
+/// falling back to classifying it on the spot if it's outside of them.
+fn get_classified<T: Density>(
+    cases: &Matrix<(usize, usize), 2>,
+    loc: Point<i32, 2>,
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> (usize, usize) {
+    let local = loc - tiles.tile_origin();
+    match [local[0], local[1]].map(usize::try_from) {
+        [Ok(x), Ok(y)] => cases.try_get([x, y]),
+        _ => None,
+    }
+    .unwrap_or_else(|| get_ruleset_and_map_id(loc, tiles, settings))
+}
+
+pub fn marching_squares<T: Density>(
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> (Vec<Point<f32, 2>>, Vec<Point<f32, 2>>) {
+    let cases = classify_tiles(tiles, settings);
+    let neighbors = [
+        [0, 0].into(),
+        [-1, 0].into(),
+        [0, -1].into(),
+        [1, 0].into(),
+        [0, 1].into(),
+    ];
+    let rows = map_rows(tiles.tile_dimension()[1], settings.parallel, |y| {
+        let mut collision_vertices = Vec::new();
+        let mut vertices = Vec::new();
+        for x in 0..tiles.tile_dimension()[0] as i32 {
+            let loc = tiles.tile_origin() + [x, y].into();
+            let (ruleset, map_id) = cases.get([x as usize, y as usize]);
+            let empty_nearby = neighbors.into_iter().any(|x| {
+                let (ruleset, map_id) = get_classified(&cases, loc + x, tiles, settings);
+                ruleset == 1 && map_id != 15
+            });
+            let collides =
+                (ruleset == 1 && map_id == 15 && empty_nearby) || (ruleset == 0 && map_id != 15);
             for point in &TRIANGLE_MAPPINGS[ruleset][map_id] {
                 let corner_indices = index_to_corner_indices(*point);
-                let prop = get_density_proportion(loc, corner_indices, tiles);
-                let rel_loc = CORNERS[corner_indices[0]].lerp(CORNERS[corner_indices[1]], prop);
-            
-                let neighbors = [
-                    [0, 0].into(),
-                    [-1, 0].into(),
-                    [0, -1].into(),
-                    [1, 0].into(),
-                    [0, 1].into()
-                ];
-
-                let l = rel_loc * tiles.dist_between_nodes() + tile_location;
-                let l = [l[0] as f32, l[1] as f32].into();
-                let empty_nearby = neighbors.into_iter().any(|x| {
-                    let (ruleset, map_id) = get_ruleset_and_map_id(loc + x, tiles);
-                    ruleset == 1 && map_id != 15
-                });
-                if (ruleset == 1 && map_id == 15 && empty_nearby) || (ruleset == 0 && map_id != 15) {
+                let l = get_vertex_location(loc, corner_indices, tiles, settings.iso);
+                if collides {
                     collision_vertices.push(l);
                 }
                 vertices.push(l);
             }
         }
+        (vertices, collision_vertices)
+    });
+    let (vertices, collision_vertices): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+    (
+        vertices.into_iter().flatten().collect(),
+        collision_vertices.into_iter().flatten().collect(),
+    )
+}
+
+/// same triangles as `marching_squares`, but vertices shared between triangles (and between
+/// neighboring tiles) are welded together. Returns the deduplicated vertices, and the indices
+/// into them (every 3 indices make up a triangle).
+pub fn marching_squares_indexed<T: Density>(
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> (Vec<Point<f32, 2>>, Vec<u32>) {
+    let cases = classify_tiles(tiles, settings);
+    let rows = map_rows(tiles.tile_dimension()[1], settings.parallel, |y| {
+        let mut corners = Vec::new();
+        for x in 0..tiles.tile_dimension()[0] as i32 {
+            let loc = tiles.tile_origin() + [x, y].into();
+            let (ruleset, map_id) = cases.get([x as usize, y as usize]);
+            for point in &TRIANGLE_MAPPINGS[ruleset][map_id] {
+                let corner_indices = index_to_corner_indices(*point);
+                corners.push((
+                    get_vertex_key(loc, corner_indices),
+                    get_vertex_location(loc, corner_indices, tiles, settings.iso),
+                ));
+            }
+        }
+        corners
+    });
+
+    // welding has to happen in order, so that the output doesn't depend on how the rows were split up
+    let mut vertex_ids = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (key, location) in rows.into_iter().flatten() {
+        let id = *vertex_ids.entry(key).or_insert_with(|| {
+            vertices.push(location);
+            (vertices.len() - 1) as u32
+        });
+        indices.push(id);
+    }
+    (vertices, indices)
+}
+
+/// signed area of the polygon described by the provided points,
+/// positive if they wind counter clockwise.
+fn signed_area(points: &[Point<f32, 2>]) -> f32 {
+    points
+        .iter()
+        .circular_tuple_windows()
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum::<f32>()
+        / 2.0
+}
+
+/// finds the outlines of the filled regions of the tiles, using the same interpolated points as
+/// `marching_squares`. Outlines that run off the edge of the tiles are returned first, and are not closed.
+pub fn marching_squares_contours<T: Density>(
+    tiles: &Tiles<T>,
+    settings: MarchingSquaresSettings<T>,
+) -> Vec<Contour> {
+    let cases = classify_tiles(tiles, settings);
+    let rows = map_rows(tiles.tile_dimension()[1], settings.parallel, |y| {
+        let mut segments = Vec::new();
+        for x in 0..tiles.tile_dimension()[0] as i32 {
+            let loc = tiles.tile_origin() + [x, y].into();
+            let (ruleset, map_id) = cases.get([x as usize, y as usize]);
+            for segment in EDGE_MAPPINGS[ruleset][map_id].chunks(2) {
+                segments.push([segment[0], segment[1]].map(|point| {
+                    let corner_indices = index_to_corner_indices(point);
+                    (
+                        get_vertex_key(loc, corner_indices),
+                        get_vertex_location(loc, corner_indices, tiles, settings.iso),
+                    )
+                }));
+            }
+        }
+        segments
+    });
+
+    let mut segments: Vec<[VertexKey; 2]> = Vec::new();
+    let mut locations = HashMap::new();
+    for segment in rows.into_iter().flatten() {
+        for (key, location) in segment {
+            locations.entry(key).or_insert(location);
+        }
+        segments.push(segment.map(|(key, _)| key));
+    }
+
+    let next: HashMap<_, _> = segments.iter().map(|[from, to]| (*from, *to)).collect();
+    let has_prev: HashSet<_> = segments.iter().map(|[_, to]| *to).collect();
+    let open_starts = segments
+        .iter()
+        .map(|[from, _]| *from)
+        .filter(|from| !has_prev.contains(from));
+    let loop_starts = segments.iter().map(|[from, _]| *from);
+
+    let mut visited = HashSet::new();
+    let mut contours = Vec::new();
+    for start in open_starts.chain(loop_starts) {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut points = Vec::new();
+        let mut key = start;
+        let closed = loop {
+            visited.insert(key);
+            points.push(locations[&key]);
+            match next.get(&key) {
+                Some(next_key) if *next_key == start => break true,
+                Some(next_key) if !visited.contains(next_key) => key = *next_key,
+                _ => break false,
+            }
+        };
+        let hole = closed && signed_area(&points) < 0.0;
+        contours.push(Contour {
+            points,
+            closed,
+            hole,
+        });
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tiles::Boundary, *};
+
+    /// (densities, connected by the asymptotic decider, connected by the center sample)
+    /// for saddles with the first and third corners filled.
+    const SADDLES: [([f32; 4], bool, bool); 3] = [
+        ([-3.0, 1.0, -3.0, 1.0], true, true),
+        ([-1.0, 3.0, -1.0, 3.0], false, false),
+        // the deciders disagree when the empty corners are lopsided
+        ([-4.0, 1.0, -4.0, 10.0], true, false),
+    ];
+
+    /// a 4x4 grid of empty densities, with the saddle in the middle tile.
+    fn saddle_grid(saddle: [f32; 4]) -> Matrix<f32, 2> {
+        Matrix::from_fn([4, 4], |loc| {
+            match CORNERS_POINT
+                .iter()
+                .position(|corner| [corner[0] + 1, corner[1] + 1] == loc.map(|x| x as i32))
+            {
+                Some(i) => saddle[i],
+                None => 1.0,
+            }
+        })
+    }
+
+    fn contour_topology(densities: &Matrix<f32, 2>, saddle: SaddleResolution) -> Vec<(bool, bool)> {
+        let settings = MarchingSquaresSettings {
+            saddle,
+            ..Default::default()
+        };
+        marching_squares_contours(&Tiles::new(densities, 1.0), settings)
+            .iter()
+            .map(|contour| (contour.closed, contour.hole))
+            .collect()
+    }
+
+    #[test]
+    fn saddle_deciders() {
+        for (densities, asymptotic, center) in SADDLES {
+            // the same saddle rotated, so that the second and fourth corners are filled
+            let [a, b, c, d] = densities;
+            for densities in [densities, [d, a, b, c]] {
+                let decide = |saddle| is_saddle_connected(densities, saddle);
+                assert!(decide(SaddleResolution::Connected));
+                assert_eq!(decide(SaddleResolution::AsymptoticDecider), asymptotic);
+                assert_eq!(decide(SaddleResolution::CenterSample), center);
+            }
+        }
+    }
+
+    #[test]
+    fn saddle_contours() {
+        for (densities, asymptotic, center) in SADDLES {
+            let grid = saddle_grid(densities);
+            for (saddle, connected) in [
+                (SaddleResolution::Connected, true),
+                (SaddleResolution::AsymptoticDecider, asymptotic),
+                (SaddleResolution::CenterSample, center),
+            ] {
+                let topology = contour_topology(&grid, saddle);
+                // connected corners share one outline, otherwise each gets its own
+                let expected = vec![(true, false); if connected { 1 } else { 2 }];
+                assert_eq!(topology, expected, "{densities:?} {saddle:?}");
+                assert_eq!(contour_topology(&grid, saddle), topology);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let densities = Matrix::from_fn([64, 48], |[x, y]| {
+            (x as f32 * 0.3).sin() * (y as f32 * 0.25).cos() + 0.1
+        });
+        let tiles = Tiles::new(&densities, 1.0);
+        let sequential = MarchingSquaresSettings::default();
+        let parallel = MarchingSquaresSettings {
+            parallel: true,
+            ..sequential
+        };
+        assert_eq!(
+            marching_squares_indexed(&tiles, sequential),
+            marching_squares_indexed(&tiles, parallel)
+        );
+        let contours = |settings| {
+            marching_squares_contours(&tiles, settings)
+                .into_iter()
+                .map(|contour| (contour.points, contour.closed, contour.hole))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(contours(sequential), contours(parallel));
+    }
+
+    #[test]
+    fn boundaries() {
+        // a filled column along the left edge of the grid
+        let densities = Matrix::from_fn([4, 3], |[x, _]| if x == 0 { -1.0 } else { 1.0 });
+        let tiles = |boundary| Tiles::new(&densities, 1.0).with_boundary(boundary);
+        // the (closed, hole) of every outline, and how far the outlines reach along x
+        let outlines = |tiles: Tiles<f32>| {
+            let contours = marching_squares_contours(&tiles, MarchingSquaresSettings::default());
+            let xs = contours
+                .iter()
+                .flat_map(|contour| &contour.points)
+                .map(|p| p[0]);
+            let range = xs.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            });
+            let topology = contours
+                .iter()
+                .map(|contour| (contour.closed, contour.hole))
+                .collect::<Vec<_>>();
+            (topology, range)
+        };
+
+        // without a boundary the column just runs off the edge
+        let open = vec![(false, false)];
+        assert_eq!(
+            outlines(Tiles::new(&densities, 1.0)),
+            (open.clone(), (0.5, 0.5))
+        );
+        // empty outside closes the column off
+        let closed = vec![(true, false)];
+        assert_eq!(
+            outlines(tiles(Boundary::Constant(1.0))),
+            (closed, (-0.5, 0.5))
+        );
+        // filled outside surrounds the rest of the grid, leaving a hole
+        let hole = vec![(true, true)];
+        assert_eq!(
+            outlines(tiles(Boundary::Constant(-1.0))),
+            (hole, (0.5, 3.5))
+        );
+        // the column carries on past the edge
+        assert_eq!(outlines(tiles(Boundary::Clamp)), (open, (0.5, 0.5)));
+        // the empty column on the right edge is mirrored to the left of the filled one
+        let both_sides = vec![(false, false); 2];
+        assert_eq!(
+            outlines(tiles(Boundary::Mirror)),
+            (both_sides.clone(), (-0.5, 0.5))
+        );
+        // the filled column is next to the right edge of the grid too
+        assert_eq!(outlines(tiles(Boundary::Wrap)), (both_sides, (0.5, 3.5)));
+    }
+
+    #[test]
+    fn empty_boundaries() {
+        let densities = Matrix::from_fn([0, 3], |_| 0.0);
+        // only a constant has anything to give outside the empty densities
+        for (boundary, outside) in [
+            (Boundary::Constant(1.0), 1.0),
+            (Boundary::Clamp, 0.0),
+            (Boundary::Wrap, 0.0),
+            (Boundary::Mirror, 0.0),
+        ] {
+            let tiles = Tiles::new(&densities, 1.0).with_boundary(boundary);
+            assert_eq!(tiles.get([-1, 0].into()), outside);
+            assert!(
+                marching_squares_contours(&tiles, MarchingSquaresSettings::default()).is_empty()
+            );
+        }
     }
-    (vertices, collision_vertices)
 }
@@ -2,26 +2,80 @@ use crate::point::{Numeric, Point};
 
 use super::matrix::Matrix;
 
-/// tilemap which returns a default density for
-/// indices outside it's range.
+/// decides which density `Tiles` returns for locations outside of its densities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary<T> {
+    /// every location outside of the densities has the same density.
+    Constant(T),
+    /// use the density at the closest location inside of the densities.
+    Clamp,
+    /// the densities repeat forever in every direction.
+    Wrap,
+    /// the densities are reflected at their edges, without repeating the edge itself.
+    Mirror,
+}
+
+/// tilemap which returns a density according to its boundary for
+/// indices outside it's range (by default, `T::default()`).
+/// By default only the tiles between the densities are meshed, picking a boundary with
+/// `with_boundary` also meshes the ring of tiles just outside of them, so that the boundary
+/// decides whether shapes touching the edge get closed off.
 pub struct Tiles<'a, T> where T : Numeric<T> + Default {
-    densities: Matrix<'a, T, 2>,
+    densities: Matrix<T, 2, &'a [T]>,
     dist_between_nodes: f64,
+    boundary: Boundary<T>,
+    mesh_boundary: bool,
 }
 
 impl<'a, T> Tiles<'a, T> where T : Numeric<T> + Default {
-    pub fn new(densities: Matrix<'a, T, 2>, dist_between_nodes: f64) -> Self {
+    pub fn new<S: AsRef<[T]>>(densities: &'a Matrix<T, 2, S>, dist_between_nodes: f64) -> Self {
         Self {
-            densities,
+            densities: densities.view(),
             dist_between_nodes,
+            boundary: Boundary::Constant(T::default()),
+            mesh_boundary: false,
         }
     }
 
-    pub fn get(&self, loc: Point<i32, 2>) -> T {
-        match [loc[0], loc[1]].map(|x| usize::try_from(x)) {
-            [Ok(x), Ok(y)] if x < self.densities.dim()[0] && y < self.densities.dim()[1] => {
-                self.densities.get([x, y])
+    pub fn with_boundary(mut self, boundary: Boundary<T>) -> Self {
+        self.boundary = boundary;
+        self.mesh_boundary = true;
+        self
+    }
+
+    /// converts a coordinate along one axis into one inside of the densities, or None if the
+    /// boundary is constant and the coordinate is out of range (or there are no densities at all).
+    fn resolve(&self, x: i32, dim: usize) -> Option<usize> {
+        if dim == 0 {
+            return None;
+        }
+        let dim = dim as i32;
+        let x = match self.boundary {
+            Boundary::Constant(_) if x < 0 || x >= dim => return None,
+            Boundary::Constant(_) => x,
+            Boundary::Clamp => x.clamp(0, dim - 1),
+            Boundary::Wrap => x.rem_euclid(dim),
+            Boundary::Mirror if dim == 1 => 0,
+            Boundary::Mirror => {
+                let period = 2 * (dim - 1);
+                let x = x.rem_euclid(period);
+                if x < dim {
+                    x
+                } else {
+                    period - x
+                }
             }
+        };
+        Some(x as usize)
+    }
+
+    pub fn get(&self, loc: Point<i32, 2>) -> T {
+        let dim = self.densities.dim();
+        let loc = [self.resolve(loc[0], dim[0]), self.resolve(loc[1], dim[1])];
+        match (loc, self.boundary) {
+            ([Some(x), Some(y)], _) => self.densities.get([x, y]),
+            (_, Boundary::Constant(density)) => density,
+            // the other boundaries only leave locations outside when there are no densities
             _ => T::default(),
         }
     }
@@ -30,6 +84,27 @@ impl<'a, T> Tiles<'a, T> where T : Numeric<T> + Default {
         self.densities.dim()
     }
 
+    /// the number of tiles along each axis. There's one less tile than there are densities,
+    /// unless the densities wrap around, in which case the last tile joins the last and first densities.
+    /// Meshing the boundary adds a tile to either side.
+    pub fn tile_dimension(&self) -> [usize; 2] {
+        self.dimension().map(|x| match self.boundary {
+            _ if x == 0 => 0,
+            Boundary::Wrap => x,
+            _ if self.mesh_boundary => x + 1,
+            _ => x - 1,
+        })
+    }
+
+    /// location of the first tile, which is outside of the densities when the boundary is meshed.
+    pub fn tile_origin(&self) -> Point<i32, 2> {
+        match self.boundary {
+            Boundary::Wrap => [0, 0].into(),
+            _ if self.mesh_boundary => [-1, -1].into(),
+            _ => [0, 0].into(),
+        }
+    }
+
     pub fn dist_between_nodes(&self) -> f64 {
         self.dist_between_nodes
     }
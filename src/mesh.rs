@@ -25,3 +25,32 @@ pub fn set_mesh_attributes_according_to_verts(mesh: &mut Mesh, verts: &[Point<f3
     );
     mesh.insert_indices(Indices::U32((0..num_verts).collect()));
 }
+
+pub fn indexed_verts_to_mesh(verts: &[Point<f32, 2>], indices: &[u32]) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all());
+    set_mesh_attributes_according_to_indexed_verts(&mut mesh, verts, indices);
+    mesh
+}
+
+pub fn set_mesh_attributes_according_to_indexed_verts(
+    mesh: &mut Mesh,
+    verts: &[Point<f32, 2>],
+    indices: &[u32],
+) {
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        verts
+            .iter()
+            .map(|p| Vec3::new(p[0], p[1], 0.0))
+            .collect::<Vec<_>>(),
+    );
+    mesh.insert_indices(Indices::U32(indices.to_vec()));
+}
+
+pub fn surface_to_mesh(positions: Vec<Vec3>, normals: Vec<Vec3>, indices: Vec<u32>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
@@ -0,0 +1,155 @@
+use bevy::math::Vec3;
+use num_traits::Float;
+
+use crate::marching_squares::matrix::Matrix;
+
+/// offsets of the 8 corners of a cell, the i-th corner is offset along
+/// every axis whose bit is set in i.
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// the 12 edges of a cell, as pairs of indices into `CORNERS`.
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+fn to_f32<T: Float>(x: T) -> f32 {
+    x.to_f32().unwrap_or(0.0)
+}
+
+/// places a single vertex inside of a cell that the surface passes through, at the average of the
+/// points where the surface crosses the edges of the cell. Also returns the normal of the surface there,
+/// which is the average gradient of the densities across the cell. Returns None if the surface
+/// doesn't pass through the cell.
+fn get_cell_vertex<T: Float>(densities: [T; 8]) -> Option<(Vec3, Vec3)> {
+    let mut sum = Vec3::ZERO;
+    let mut crossings = 0;
+    for [a, b] in EDGES {
+        if (densities[a] <= T::zero()) == (densities[b] <= T::zero()) {
+            continue;
+        }
+        let prop = to_f32(densities[a] / (densities[a] - densities[b]));
+        let corners = [a, b].map(|i| Vec3::from_array(CORNERS[i].map(|x| x as f32)));
+        sum += corners[0].lerp(corners[1], prop);
+        crossings += 1;
+    }
+    if crossings == 0 {
+        return None;
+    }
+
+    let mut gradient = Vec3::ZERO;
+    for i in 0..4 {
+        // the low corner of the i-th edge along each axis
+        let x = [0, 2, 4, 6][i];
+        let y = [0, 1, 4, 5][i];
+        let z = [0, 1, 2, 3][i];
+        gradient += Vec3::new(
+            to_f32(densities[x + 1] - densities[x]),
+            to_f32(densities[y + 2] - densities[y]),
+            to_f32(densities[z + 4] - densities[z]),
+        );
+    }
+    Some((sum / crossings as f32, gradient.normalize_or_zero()))
+}
+
+/// extracts the surface of a 3 dimensional density field using surface nets.
+/// like `marching_squares`, densities at or below the iso level are filled.
+/// Returns the vertex positions, their (outward facing) normals, and the indices
+/// into them (every 3 indices make up a counter clockwise triangle).
+pub fn surface_nets<T: Float, S: AsRef<[T]>>(
+    densities: &Matrix<T, 3, S>,
+    dist_between_nodes: f32,
+    iso: T,
+) -> (Vec<Vec3>, Vec<Vec3>, Vec<u32>) {
+    let dim = densities.dim();
+    if dim.iter().any(|x| *x < 2) {
+        return (Vec::new(), Vec::new(), Vec::new());
+    }
+    let cells = dim.map(|x| x - 1);
+    let cell_index = |loc: [usize; 3]| loc[0] + cells[0] * (loc[1] + cells[1] * loc[2]);
+    let get = |loc: [usize; 3]| densities.get(loc) - iso;
+
+    // place a vertex in every cell the surface passes through
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut vertex_ids = vec![None; cells.iter().product()];
+    for z in 0..cells[2] {
+        for y in 0..cells[1] {
+            for x in 0..cells[0] {
+                let corners = CORNERS.map(|c| get([x + c[0], y + c[1], z + c[2]]));
+                if let Some((position, normal)) = get_cell_vertex(corners) {
+                    let loc = Vec3::new(x as f32, y as f32, z as f32);
+                    vertex_ids[cell_index([x, y, z])] = Some(positions.len() as u32);
+                    positions.push((loc + position) * dist_between_nodes);
+                    normals.push(normal);
+                }
+            }
+        }
+    }
+
+    // connect the vertices of the 4 cells around every grid edge the surface crosses
+    let mut indices = Vec::new();
+    for z in 0..dim[2] {
+        for y in 0..dim[1] {
+            for x in 0..dim[0] {
+                let loc = [x, y, z];
+                for axis in 0..3 {
+                    let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+                    if loc[axis] + 1 >= dim[axis]
+                        || loc[b] == 0
+                        || loc[c] == 0
+                        || loc[b] >= cells[b]
+                        || loc[c] >= cells[c]
+                    {
+                        continue;
+                    }
+                    let mut next = loc;
+                    next[axis] += 1;
+                    let filled = get(loc) <= T::zero();
+                    if filled == (get(next) <= T::zero()) {
+                        continue;
+                    }
+
+                    let offset = |db: usize, dc: usize| {
+                        let mut cell = loc;
+                        cell[b] -= db;
+                        cell[c] -= dc;
+                        vertex_ids[cell_index(cell)]
+                    };
+                    // counter clockwise when viewed from the positive side of the axis
+                    let quad = [offset(1, 1), offset(0, 1), offset(0, 0), offset(1, 0)];
+                    let Some(quad) = quad.into_iter().collect::<Option<Vec<_>>>() else {
+                        continue;
+                    };
+                    // the surface faces away from the filled side
+                    let triangles = if filled {
+                        [quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]
+                    } else {
+                        [quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]
+                    };
+                    indices.extend(triangles);
+                }
+            }
+        }
+    }
+    (positions, normals, indices)
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::PrimaryWindow,
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chain::plugin::BlocksChains,
+    field_collider::{FieldColliderShape, FieldContours, FilledField},
+    marching_squares::{
+        marching_squares_contours, marching_squares_indexed, matrix::Matrix, tiles::Tiles,
+        MarchingSquaresSettings,
+    },
+    mesh::{set_mesh_attributes_according_to_indexed_verts, verts_to_mesh},
+};
+
+/// number of tiles along each side of a chunk.
+const CHUNK_TILES: usize = 32;
+
+/// number of densities along each side of a chunk. Neighboring chunks
+/// both store the densities along the edge they share, so their meshes line up.
+const CHUNK_NODES: usize = CHUNK_TILES + 1;
+
+/// densities are kept within this range, so that digging out
+/// and filling back in always take the same amount of effort.
+const MAX_DENSITY: f32 = 1.0;
+
+/// a large density grid, split up into fixed size chunks that are meshed independently.
+/// The chunks are children of the terrain entity.
+#[derive(Component)]
+pub struct Terrain {
+    chunks: HashMap<IVec2, Entity>,
+    dist_between_nodes: f32,
+}
+
+#[derive(Component)]
+pub struct TerrainChunk {
+    densities: Matrix<f32, 2>,
+    dirty: bool,
+}
+
+/// changes the density of the terrain within a circle, negative strengths fill the terrain in,
+/// and positive strengths dig it out. The center is in world space.
+#[derive(Event)]
+pub struct TerrainEdit {
+    pub center: Vec2,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl Terrain {
+    /// size of a chunk in world units.
+    fn chunk_size(&self) -> f32 {
+        CHUNK_TILES as f32 * self.dist_between_nodes
+    }
+}
+
+/// the starting shape of the terrain, walls around the edge of the room and a couple of pillars.
+fn initial_density(pos: Vec2) -> f32 {
+    let walls = 440.0 - pos.x.abs().max(pos.y.abs());
+    let pillars = [Vec2::new(-250.0, 150.0), Vec2::new(250.0, 150.0)]
+        .into_iter()
+        .map(|center| pos.distance(center) - 50.0)
+        .fold(f32::INFINITY, f32::min);
+    (walls.min(pillars) / 16.0).clamp(-MAX_DENSITY, MAX_DENSITY)
+}
+
+pub fn setup_terrain(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let chunk_counts = IVec2::new(8, 8);
+    let dist_between_nodes = 4.0;
+    let chunk_size = CHUNK_TILES as f32 * dist_between_nodes;
+    let origin = -chunk_counts.as_vec2() * chunk_size / 2.0;
+    let material = materials.add(Color::rgb(0.3, 0.25, 0.3));
+
+    let mut terrain = Terrain {
+        chunks: HashMap::new(),
+        dist_between_nodes,
+    };
+    let terrain_entity = commands
+        .spawn(SpatialBundle::from_transform(Transform::from_translation(
+            origin.extend(-1.0),
+        )))
+        .id();
+    for y in 0..chunk_counts.y {
+        for x in 0..chunk_counts.x {
+            let coord = IVec2::new(x, y);
+            let chunk_origin = origin + coord.as_vec2() * chunk_size;
+            let densities = Matrix::from_fn([CHUNK_NODES, CHUNK_NODES], |[nx, ny]| {
+                let pos = Vec2::new(nx as f32, ny as f32) * dist_between_nodes;
+                initial_density(chunk_origin + pos)
+            });
+            let chunk = commands
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: meshes.add(verts_to_mesh(&[])).into(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(
+                            (coord.as_vec2() * chunk_size).extend(0.0),
+                        ),
+                        ..default()
+                    },
+                    TerrainChunk {
+                        densities,
+                        dirty: true,
+                    },
+                    FieldContours::default(),
+                    FilledField::default(),
+                    FieldColliderShape::Polyline,
+                    BlocksChains,
+                ))
+                .id();
+            commands.entity(terrain_entity).add_child(chunk);
+            terrain.chunks.insert(coord, chunk);
+        }
+    }
+
+    // the terrain can be dug out all the way to its edge, so keep things
+    // from escaping the room with walls just outside of it.
+    let size = chunk_counts.as_vec2() * chunk_size;
+    let half = size / 2.0;
+    let thickness = 16.0;
+    for (center, half_extents) in [
+        (Vec2::new(half.x, -thickness), Vec2::new(half.x + thickness * 2.0, thickness)),
+        (Vec2::new(half.x, size.y + thickness), Vec2::new(half.x + thickness * 2.0, thickness)),
+        (Vec2::new(-thickness, half.y), Vec2::new(thickness, half.y)),
+        (Vec2::new(size.x + thickness, half.y), Vec2::new(thickness, half.y)),
+    ] {
+        let wall = commands
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_translation(center.extend(0.0))),
+                Collider::cuboid(half_extents.x, half_extents.y),
+            ))
+            .id();
+        commands.entity(terrain_entity).add_child(wall);
+    }
+    commands.entity(terrain_entity).insert(terrain);
+}
+
+/// dig out terrain with the left mouse button, and fill it in with the right.
+pub fn terrain_brush(
+    buttons: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut edits: EventWriter<TerrainEdit>,
+) {
+    let strength = if buttons.pressed(MouseButton::Left) {
+        0.2
+    } else if buttons.pressed(MouseButton::Right) {
+        -0.2
+    } else {
+        return;
+    };
+    let (camera, camera_transform) = camera.single();
+    let Some(center) = window
+        .single()
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+    edits.send(TerrainEdit {
+        center,
+        radius: 30.0,
+        strength,
+    });
+}
+
+pub fn apply_terrain_edits(
+    mut edits: EventReader<TerrainEdit>,
+    terrain: Query<(&Terrain, &GlobalTransform)>,
+    mut chunks: Query<&mut TerrainChunk>,
+) {
+    let (terrain, terrain_transform) = terrain.single();
+    for edit in edits.read() {
+        let center = edit.center - terrain_transform.translation().xy();
+        // chunks include the densities on both of their edges, so a chunk is affected
+        // even if the edit only reaches its far edge.
+        let min_chunk = ((center - edit.radius) / terrain.chunk_size()).ceil().as_ivec2() - 1;
+        let max_chunk = ((center + edit.radius) / terrain.chunk_size()).floor().as_ivec2();
+        for y in min_chunk.y..=max_chunk.y {
+            for x in min_chunk.x..=max_chunk.x {
+                let coord = IVec2::new(x, y);
+                let Some(mut chunk) = terrain
+                    .chunks
+                    .get(&coord)
+                    .and_then(|entity| chunks.get_mut(*entity).ok())
+                else {
+                    continue;
+                };
+                let local_center = center - coord.as_vec2() * terrain.chunk_size();
+                apply_edit_to_chunk(&mut chunk, terrain.dist_between_nodes, local_center, edit);
+            }
+        }
+    }
+}
+
+/// applies the edit to the densities of a chunk, the center is relative to the chunk.
+fn apply_edit_to_chunk(
+    chunk: &mut TerrainChunk,
+    dist_between_nodes: f32,
+    center: Vec2,
+    edit: &TerrainEdit,
+) {
+    let min = ((center - edit.radius) / dist_between_nodes).ceil().max(Vec2::ZERO);
+    let max = ((center + edit.radius) / dist_between_nodes)
+        .floor()
+        .min(Vec2::splat((CHUNK_NODES - 1) as f32));
+    let mut changed = false;
+    for y in min.y as usize..=max.y as usize {
+        for x in min.x as usize..=max.x as usize {
+            let pos = Vec2::new(x as f32, y as f32) * dist_between_nodes;
+            let falloff = 1.0 - pos.distance_squared(center) / (edit.radius * edit.radius);
+            if falloff <= 0.0 {
+                continue;
+            }
+            let density = chunk.densities.get_mut([x, y]);
+            *density = (*density + edit.strength * falloff * falloff)
+                .clamp(-MAX_DENSITY, MAX_DENSITY);
+            changed = true;
+        }
+    }
+    chunk.dirty |= changed;
+}
+
+/// rebuilds the mesh and collider outlines of every chunk that's been edited since it was last meshed.
+pub fn remesh_terrain_chunks(
+    terrain: Query<&Terrain>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunks: Query<(
+        &mut TerrainChunk,
+        &Mesh2dHandle,
+        &mut FieldContours,
+        &mut FilledField,
+    )>,
+) {
+    let terrain = terrain.single();
+    for (mut chunk, mesh_handle, mut contours, mut filled) in chunks.iter_mut() {
+        if !chunk.dirty {
+            continue;
+        }
+        chunk.dirty = false;
+
+        let tiles = Tiles::new(&chunk.densities, terrain.dist_between_nodes as f64);
+        let settings = MarchingSquaresSettings::default();
+        let (verts, indices) = marching_squares_indexed(&tiles, settings);
+        if let Some(mesh) = meshes.get_mut(mesh_handle.0.id()) {
+            set_mesh_attributes_according_to_indexed_verts(mesh, &verts, &indices);
+        }
+        contours.0 = marching_squares_contours(&tiles, settings);
+        // without any outlines the chunk is either completely filled in or completely dug out
+        let is_filled = contours.0.is_empty() && chunk.densities.get([0, 0]) <= settings.iso;
+        filled.0 =
+            is_filled.then(|| Rect::from_corners(Vec2::ZERO, Vec2::splat(terrain.chunk_size())));
+    }
+}